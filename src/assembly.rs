@@ -0,0 +1,212 @@
+use alloc::{string::String, vec, vec::Vec};
+use core::fmt::Write;
+
+use crate::{
+    instructions::{
+        add_instruction::AddInstruction, copy_instruction::CopyInstruction,
+        delta_instruction::DeltaInstruction, offset_copy_instruction::OffsetCopyInstruction,
+        remove_instruction::RemoveInstruction, InstructionError, InstructionInfo, Result,
+    },
+    patch::Patch,
+};
+
+const REMOVE_MNEMONIC: &str = "remove";
+const ADD_MNEMONIC: &str = "add";
+const COPY_MNEMONIC: &str = "copy";
+const OFFSET_COPY_MNEMONIC: &str = "offset_copy";
+const COMMENT_SIGN: char = '#';
+
+fn write_string(text: &mut String, content: &[u8]) {
+    text.push('"');
+    for item in content {
+        match item {
+            b'"' => text.push_str("\\\""),
+            b'\\' => text.push_str("\\\\"),
+            b'\n' => text.push_str("\\n"),
+            b'\r' => text.push_str("\\r"),
+            b'\t' => text.push_str("\\t"),
+            b' '..=b'~' => text.push(*item as char),
+            _ => write!(text, "\\x{:02x}", item).unwrap(),
+        }
+    }
+    text.push('"');
+}
+
+fn read_string(text: &str) -> Option<Vec<u8>> {
+    let text = text.strip_prefix('"')?.strip_suffix('"')?;
+    let mut content: Vec<u8> = Vec::with_capacity(text.len());
+    let mut bytes = text.bytes();
+    while let Some(item) = bytes.next() {
+        let item = match item {
+            b'\\' => match bytes.next()? {
+                b'"' => b'"',
+                b'\\' => b'\\',
+                b'n' => b'\n',
+                b'r' => b'\r',
+                b't' => b'\t',
+                b'x' => {
+                    let digits = [bytes.next()?, bytes.next()?];
+                    u8::from_str_radix(core::str::from_utf8(&digits).ok()?, 16).ok()?
+                }
+                _ => return None,
+            },
+            b'"' => return None,
+            _ => item,
+        };
+        content.push(item);
+    }
+    Some(content)
+}
+
+fn read_diff(text: &str) -> Option<Vec<u8>> {
+    text.strip_prefix("diff=[")?
+        .strip_suffix(']')?
+        .split_whitespace()
+        .map(|item| u8::from_str_radix(item, 16).ok())
+        .collect()
+}
+
+fn read_instruction(text: &str) -> Option<DeltaInstruction> {
+    let (mnemonic, operands) = text.split_once(' ').unwrap_or((text, ""));
+    let operands = operands.trim();
+    match mnemonic {
+        REMOVE_MNEMONIC => Some(RemoveInstruction::new(operands.parse().ok()?).into()),
+        ADD_MNEMONIC => {
+            let content = read_string(operands)?;
+            (content.len() <= u8::MAX as usize).then(|| AddInstruction::new(content).into())
+        }
+        COPY_MNEMONIC => {
+            let (length, diff) = operands.split_once(' ').unwrap_or((operands, ""));
+            let length: u8 = length.parse().ok()?;
+            let content = match diff.trim() {
+                "" => vec![0; length as usize],
+                diff => read_diff(diff)?,
+            };
+            (content.len() == length as usize).then(|| CopyInstruction::new(content).into())
+        }
+        OFFSET_COPY_MNEMONIC => {
+            let (length, offset) = operands.split_once(' ')?;
+            let length: u8 = length.parse().ok()?;
+            let offset: u64 = offset.trim().strip_prefix("offset=")?.parse().ok()?;
+            offset.checked_add(length as u64)?;
+            Some(OffsetCopyInstruction::new(offset, length).into())
+        }
+        _ => None,
+    }
+}
+
+impl Patch {
+    pub fn to_assembly(&self) -> String {
+        let mut text = String::new();
+        for instruction in self.instructions() {
+            match instruction {
+                DeltaInstruction::Remove(instruction) => {
+                    write!(text, "{} {}", REMOVE_MNEMONIC, instruction.len()).unwrap()
+                }
+                DeltaInstruction::Add(instruction) => {
+                    write!(text, "{} ", ADD_MNEMONIC).unwrap();
+                    write_string(&mut text, instruction.content());
+                }
+                DeltaInstruction::Copy(instruction) => {
+                    write!(text, "{} {}", COPY_MNEMONIC, instruction.len()).unwrap();
+                    if instruction.non_default_item_count() != Some(0) {
+                        text.push_str(" diff=[");
+                        for (index, item) in instruction.content().iter().enumerate() {
+                            if index > 0 {
+                                text.push(' ');
+                            }
+                            write!(text, "{:02x}", item).unwrap();
+                        }
+                        text.push(']');
+                    }
+                }
+                DeltaInstruction::OffsetCopy(instruction) => write!(
+                    text,
+                    "{} {} offset={}",
+                    OFFSET_COPY_MNEMONIC,
+                    instruction.len(),
+                    instruction.offset()
+                )
+                .unwrap(),
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    pub fn from_assembly(text: &str) -> Result<Self> {
+        let mut instructions: Vec<DeltaInstruction> = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(COMMENT_SIGN) {
+                continue;
+            }
+            let instruction = read_instruction(line)
+                .ok_or(InstructionError::InvalidAssembly { line: index + 1 })?;
+            instructions.push(instruction);
+        }
+        Ok(Patch::from_instructions(instructions))
+    }
+}
+
+#[cfg(test)]
+mod assembly_tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn to_assembly() {
+        let patch = Patch::from_instructions(vec![
+            RemoveInstruction::new(5).into(),
+            AddInstruction::new(b"say \"hi\"\n\0".to_vec()).into(),
+            CopyInstruction::new(vec![0, 0, 3]).into(),
+            CopyInstruction::new(vec![0; 4]).into(),
+            OffsetCopyInstruction::new(10, 2).into(),
+        ]);
+        let text = patch.to_assembly();
+        assert_eq!(
+            text,
+            "remove 5\n\
+             add \"say \\\"hi\\\"\\n\\x00\"\n\
+             copy 3 diff=[00 00 03]\n\
+             copy 4\n\
+             offset_copy 2 offset=10\n"
+        );
+        assert_eq!(Patch::from_assembly(&text), Ok(patch));
+    }
+
+    #[test]
+    fn from_assembly() {
+        let text = "# hand edited\n\n  copy 2 diff=[ff 01]\nadd \"\\x41B\"\n";
+        let patch = Patch::from_assembly(text).unwrap();
+        assert_eq!(patch.apply(b"AB").unwrap(), b"@CAB");
+    }
+
+    #[test]
+    fn from_assembly_err() {
+        for (text, line) in [
+            ("remove 256", 1),
+            ("copy 1\nmove 1", 2),
+            ("\nadd hello", 2),
+            ("add \"\\q\"", 1),
+            ("copy 2 diff=[00]", 1),
+            ("copy 1 diff=[0g]", 1),
+            ("offset_copy 1", 1),
+            ("offset_copy 1 offset=18446744073709551615", 1),
+        ] {
+            assert_eq!(
+                Patch::from_assembly(text),
+                Err(InstructionError::InvalidAssembly { line })
+            );
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let source = fs::read("files/source.txt").unwrap();
+        let target = fs::read("files/target.txt").unwrap();
+        let patch = Patch::new(&source, &target);
+        assert_eq!(Patch::from_assembly(&patch.to_assembly()), Ok(patch));
+    }
+}
@@ -0,0 +1,357 @@
+use std::io::{self, SeekFrom};
+
+use bytes::{Bytes, BytesMut};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt, AsyncWrite, AsyncWriteExt};
+use tokio_util::codec::{Decoder, Encoder, LengthDelimitedCodec};
+
+use crate::{
+    instructions::{
+        content_length, delta_instruction::DeltaInstruction, InstructionBytes, InstructionError,
+        InstructionInfo,
+    },
+    patch::Patch,
+};
+
+const READ_CAPACITY: usize = 1 << 13;
+
+#[derive(Debug, Default, Clone)]
+pub struct InstructionCodec {
+    index: usize,
+    offset: usize,
+}
+
+impl InstructionCodec {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn error(&self, error: InstructionError, sign: u8) -> io::Error {
+        error.at(self.index, self.offset, sign).into()
+    }
+}
+
+impl Decoder for InstructionCodec {
+    type Item = DeltaInstruction;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> io::Result<Option<Self::Item>> {
+        let Some(sign) = src.first().copied() else {
+            return Ok(None);
+        };
+        let length = src.get(1).copied().unwrap_or_default();
+        let byte_length =
+            2 + content_length(sign, length).map_err(|error| self.error(error, sign))?;
+        if src.len() < byte_length {
+            src.reserve(byte_length - src.len());
+            return Ok(None);
+        }
+        let bytes = src.split_to(byte_length);
+        let instruction =
+            DeltaInstruction::try_from(&bytes[..]).map_err(|error| self.error(error, sign))?;
+        self.index += 1;
+        self.offset += byte_length;
+        Ok(Some(instruction))
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> io::Result<Option<Self::Item>> {
+        match self.decode(src)? {
+            Some(instruction) => Ok(Some(instruction)),
+            None if src.is_empty() => Ok(None),
+            None if src.len() < 2 => Err(self.error(InstructionError::MissingLength, src[0])),
+            None => Err(self.error(InstructionError::MissingContent, src[0])),
+        }
+    }
+}
+
+impl Encoder<&DeltaInstruction> for InstructionCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, item: &DeltaInstruction, dst: &mut BytesMut) -> io::Result<()> {
+        dst.extend_from_slice(&item.to_bytes());
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct PatchCodec {
+    frames: LengthDelimitedCodec,
+}
+
+impl PatchCodec {
+    pub fn new() -> Self {
+        Self {
+            frames: LengthDelimitedCodec::new(),
+        }
+    }
+
+    pub fn with_max_length(max_length: usize) -> Self {
+        Self {
+            frames: LengthDelimitedCodec::builder()
+                .max_frame_length(max_length)
+                .new_codec(),
+        }
+    }
+}
+
+impl Default for PatchCodec {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Decoder for PatchCodec {
+    type Item = Patch;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> io::Result<Option<Self::Item>> {
+        match self.frames.decode(src)? {
+            Some(frame) => Patch::try_from_bytes(&frame)
+                .map(Some)
+                .map_err(io::Error::from),
+            None => Ok(None),
+        }
+    }
+}
+
+impl Encoder<&Patch> for PatchCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, item: &Patch, dst: &mut BytesMut) -> io::Result<()> {
+        self.frames.encode(Bytes::from(item.to_bytes()), dst)
+    }
+}
+
+struct AsyncApplier<'a, S> {
+    source: &'a mut S,
+    source_length: u64,
+    position: u64,
+    cursor: u64,
+    buffer: Vec<u8>,
+}
+
+impl<'a, S: AsyncRead + AsyncSeek + Unpin> AsyncApplier<'a, S> {
+    async fn new(source: &'a mut S) -> io::Result<Self> {
+        let source_length = source.seek(SeekFrom::End(0)).await?;
+        Ok(Self {
+            source,
+            source_length,
+            position: 0,
+            cursor: source_length,
+            buffer: Vec::with_capacity(u8::MAX as usize),
+        })
+    }
+
+    async fn read(&mut self, offset: u64, length: usize) -> io::Result<()> {
+        let end = offset
+            .checked_add(length as u64)
+            .filter(|end| *end <= self.source_length)
+            .ok_or(InstructionError::SourceMismatch)?;
+        if self.cursor != offset {
+            self.source.seek(SeekFrom::Start(offset)).await?;
+        }
+        self.buffer.resize(length, 0);
+        self.source.read_exact(&mut self.buffer).await?;
+        self.cursor = end;
+        Ok(())
+    }
+
+    async fn apply<W: AsyncWrite + Unpin>(
+        &mut self,
+        instruction: &DeltaInstruction,
+        target: &mut W,
+    ) -> io::Result<()> {
+        let length = instruction.len() as usize;
+        match instruction {
+            DeltaInstruction::Remove(_) => self.position += length as u64,
+            DeltaInstruction::Add(instruction) => target.write_all(instruction.content()).await?,
+            DeltaInstruction::Copy(instruction) => {
+                self.read(self.position, length).await?;
+                for (item, difference) in self.buffer.iter_mut().zip(instruction.content()) {
+                    *item = item.wrapping_add(*difference);
+                }
+                target.write_all(&self.buffer).await?;
+                self.position += length as u64;
+            }
+            DeltaInstruction::OffsetCopy(instruction) => {
+                self.read(instruction.offset(), length).await?;
+                target.write_all(&self.buffer).await?;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(&self) -> io::Result<()> {
+        if self.position != self.source_length {
+            return Err(InstructionError::SourceMismatch.into());
+        }
+        Ok(())
+    }
+}
+
+impl Patch {
+    pub async fn new_async(source: Vec<u8>, target: Vec<u8>) -> io::Result<Self> {
+        tokio::task::spawn_blocking(move || Patch::new(&source, &target))
+            .await
+            .map_err(io::Error::other)
+    }
+
+    pub async fn apply_async<S, W>(&self, source: &mut S, target: &mut W) -> io::Result<()>
+    where
+        S: AsyncRead + AsyncSeek + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        let mut applier = AsyncApplier::new(source).await?;
+        if applier.source_length != self.source_lenth() as u64 {
+            return Err(InstructionError::SourceMismatch.into());
+        }
+        for instruction in self.instructions() {
+            applier.apply(instruction, target).await?;
+        }
+        target.flush().await
+    }
+
+    pub async fn apply_stream<P, S, W>(
+        patch: &mut P,
+        source: &mut S,
+        target: &mut W,
+    ) -> io::Result<()>
+    where
+        P: AsyncRead + Unpin,
+        S: AsyncRead + AsyncSeek + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        let mut applier = AsyncApplier::new(source).await?;
+        let mut codec = InstructionCodec::new();
+        let mut buffer = BytesMut::with_capacity(READ_CAPACITY);
+        loop {
+            while let Some(instruction) = codec.decode(&mut buffer)? {
+                applier.apply(&instruction, target).await?;
+            }
+            if patch.read_buf(&mut buffer).await? == 0 {
+                while let Some(instruction) = codec.decode_eof(&mut buffer)? {
+                    applier.apply(&instruction, target).await?;
+                }
+                break;
+            }
+        }
+        applier.finish()?;
+        target.flush().await
+    }
+}
+
+#[cfg(test)]
+mod async_io_tests {
+    use std::{fs, io::Cursor};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn apply_async() {
+        let source = fs::read("files/source.txt").unwrap();
+        let target = fs::read("files/target.txt").unwrap();
+        let patch = Patch::new_async(source.clone(), target.clone())
+            .await
+            .unwrap();
+        assert_eq!(patch, Patch::new(&source, &target));
+
+        let mut output: Vec<u8> = Vec::new();
+        patch
+            .apply_async(&mut Cursor::new(&source), &mut output)
+            .await
+            .unwrap();
+        assert_eq!(output, target);
+
+        let error = patch
+            .apply_async(&mut Cursor::new(&target), &mut Vec::new())
+            .await
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[tokio::test]
+    async fn apply_stream() {
+        let source = fs::read("files/source.txt").unwrap();
+        let target = fs::read("files/target.txt").unwrap();
+        let bytes = Patch::new(&source, &target).to_bytes();
+        let mut output: Vec<u8> = Vec::new();
+        Patch::apply_stream(
+            &mut bytes.as_slice(),
+            &mut Cursor::new(&source),
+            &mut output,
+        )
+        .await
+        .unwrap();
+        assert_eq!(output, target);
+
+        let mut bytes = b"+\x01A@\x02".to_vec();
+        bytes.extend(1u64.to_be_bytes());
+        bytes.extend(b"-\x03");
+        let mut output: Vec<u8> = Vec::new();
+        Patch::apply_stream(&mut bytes.as_slice(), &mut Cursor::new(b"XYZ"), &mut output)
+            .await
+            .unwrap();
+        assert_eq!(output, b"AYZ");
+
+        let error = Patch::apply_stream(
+            &mut &b"-\x02"[..],
+            &mut Cursor::new(b"XYZ"),
+            &mut Vec::new(),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        let mut source = Cursor::new(b"XYZ");
+        let mut applier = AsyncApplier::new(&mut source).await.unwrap();
+        for (offset, length) in [(u64::MAX, 3), (2, 2)] {
+            let error = applier.read(offset, length).await.unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn instruction_codec() {
+        let mut codec = InstructionCodec::new();
+        let mut bytes = BytesMut::from(&b"+\x02A"[..]);
+        assert_eq!(codec.decode(&mut bytes).unwrap(), None);
+        bytes.extend_from_slice(b"B-");
+        assert_eq!(
+            codec.decode(&mut bytes).unwrap(),
+            Some(DeltaInstruction::try_from(&b"+\x02AB"[..]).unwrap())
+        );
+        assert_eq!(codec.decode(&mut bytes).unwrap(), None);
+        let error = codec.decode_eof(&mut bytes).unwrap_err();
+        assert_eq!(
+            error.into_inner().unwrap().to_string(),
+            InstructionError::MissingLength.at(1, 4, b'-').to_string()
+        );
+
+        let mut bytes = BytesMut::from(&b"?"[..]);
+        assert_eq!(
+            codec.decode(&mut bytes).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+
+        let instruction = DeltaInstruction::try_from(&b"|\x01\x00"[..]).unwrap();
+        let mut bytes = BytesMut::new();
+        codec.encode(&instruction, &mut bytes).unwrap();
+        assert_eq!(&bytes[..], b"|\x01\x00");
+    }
+
+    #[test]
+    fn patch_codec() {
+        let patch = Patch::new(b"ABCD", b"ABXD");
+        let mut codec = PatchCodec::new();
+        let mut bytes = BytesMut::new();
+        codec.encode(&patch, &mut bytes).unwrap();
+        codec.encode(&patch, &mut bytes).unwrap();
+        assert_eq!(bytes[..4], (patch.to_bytes().len() as u32).to_be_bytes());
+        assert_eq!(codec.decode(&mut bytes).unwrap(), Some(patch.clone()));
+        assert_eq!(codec.decode(&mut bytes).unwrap(), Some(patch));
+        assert_eq!(codec.decode(&mut bytes).unwrap(), None);
+
+        let mut codec = PatchCodec::with_max_length(1);
+        let mut bytes = BytesMut::from(&[0, 0, 0, 2, b'-', 1][..]);
+        assert!(codec.decode(&mut bytes).is_err());
+    }
+}
@@ -0,0 +1,377 @@
+use std::io::Write;
+
+use bzip2::{read::BzDecoder, write::BzEncoder};
+
+use crate::{
+    builder::PatchBuilder,
+    container::{read_limited, MAX_DECOMPRESSED_LENGTH},
+    instructions::{
+        delta_instruction::DeltaInstruction, InstructionError, InstructionInfo, Result,
+    },
+    patch::Patch,
+};
+
+const BSDIFF_MAGIC: &[u8; 8] = b"BSDIFF40";
+const BSDIFF_HEADER_LENGTH: usize = 32;
+
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+struct Control {
+    diff_length: i64,
+    extra_length: i64,
+    seek: i64,
+}
+
+impl Control {
+    fn is_empty(&self) -> bool {
+        *self == Control::default()
+    }
+}
+
+fn offset_to_bytes(value: i64) -> [u8; 8] {
+    let mut bytes = value.unsigned_abs().to_le_bytes();
+    if value < 0 {
+        bytes[7] |= 0x80;
+    }
+    bytes
+}
+
+fn offset_from_bytes(bytes: &[u8]) -> i64 {
+    let mut magnitude = [0u8; 8];
+    magnitude.copy_from_slice(&bytes[..8]);
+    magnitude[7] &= 0x7F;
+    let value = i64::from_le_bytes(magnitude);
+    if bytes[7] & 0x80 != 0 {
+        -value
+    } else {
+        value
+    }
+}
+
+fn compress(bytes: &[u8]) -> Vec<u8> {
+    let mut encoder = BzEncoder::new(Vec::new(), bzip2::Compression::best());
+    encoder.write_all(bytes).unwrap();
+    encoder.finish().unwrap()
+}
+
+fn decompress(bytes: &[u8], limit: usize) -> Result<Vec<u8>> {
+    read_limited(BzDecoder::new(bytes), limit)
+}
+
+impl Patch {
+    pub fn to_bsdiff(&self) -> Vec<u8> {
+        let mut controls: Vec<Control> = Vec::new();
+        let mut diff: Vec<u8> = Vec::new();
+        let mut extra: Vec<u8> = Vec::new();
+        let mut control = Control::default();
+        let mut position = 0i64;
+        let mut source_position = 0i64;
+        for instruction in self.instructions().iter() {
+            let zeros: Vec<u8>;
+            let (start, difference) = match instruction {
+                DeltaInstruction::Copy(copy) => {
+                    position += copy.len() as i64;
+                    (position - copy.len() as i64, copy.content())
+                }
+                DeltaInstruction::OffsetCopy(copy) => {
+                    zeros = vec![0; copy.len() as usize];
+                    (copy.offset() as i64, &zeros[..])
+                }
+                DeltaInstruction::Add(add) => {
+                    if control.seek != 0 {
+                        controls.push(control);
+                        control = Control::default();
+                    }
+                    control.extra_length += add.len() as i64;
+                    extra.extend_from_slice(add.content());
+                    continue;
+                }
+                DeltaInstruction::Remove(remove) => {
+                    position += remove.len() as i64;
+                    continue;
+                }
+            };
+            control.seek += start - source_position;
+            if control.extra_length > 0 || control.seek != 0 {
+                controls.push(control);
+                control = Control::default();
+            }
+            control.diff_length += difference.len() as i64;
+            diff.extend_from_slice(difference);
+            source_position = start + difference.len() as i64;
+        }
+        if !control.is_empty() {
+            controls.push(control);
+        }
+
+        let mut control_bytes: Vec<u8> = Vec::with_capacity(controls.len() * 24);
+        for control in controls.iter() {
+            control_bytes.extend(offset_to_bytes(control.diff_length));
+            control_bytes.extend(offset_to_bytes(control.extra_length));
+            control_bytes.extend(offset_to_bytes(control.seek));
+        }
+        let control_block = compress(&control_bytes);
+        let diff_block = compress(&diff);
+        let extra_block = compress(&extra);
+
+        let mut bytes: Vec<u8> = Vec::with_capacity(
+            BSDIFF_HEADER_LENGTH + control_block.len() + diff_block.len() + extra_block.len(),
+        );
+        bytes.extend_from_slice(BSDIFF_MAGIC);
+        bytes.extend(offset_to_bytes(control_block.len() as i64));
+        bytes.extend(offset_to_bytes(diff_block.len() as i64));
+        bytes.extend(offset_to_bytes(self.target_length() as i64));
+        bytes.extend(control_block);
+        bytes.extend(diff_block);
+        bytes.extend(extra_block);
+        bytes
+    }
+
+    pub fn from_bsdiff(bytes: &[u8], source: &[u8]) -> Result<Self> {
+        if bytes.len() < BSDIFF_HEADER_LENGTH || !bytes.starts_with(BSDIFF_MAGIC) {
+            return Err(InstructionError::InvalidHeader);
+        }
+        let control_length = offset_from_bytes(&bytes[8..16]);
+        let diff_length = offset_from_bytes(&bytes[16..24]);
+        let target_length = offset_from_bytes(&bytes[24..32]);
+        if control_length < 0
+            || diff_length < 0
+            || !(0..=MAX_DECOMPRESSED_LENGTH as i64).contains(&target_length)
+        {
+            return Err(InstructionError::InvalidHeader);
+        }
+        let target_length = target_length as usize;
+        let control_end = BSDIFF_HEADER_LENGTH
+            .checked_add(control_length as usize)
+            .filter(|end| *end <= bytes.len())
+            .ok_or(InstructionError::MissingContent)?;
+        let diff_end = control_end
+            .checked_add(diff_length as usize)
+            .filter(|end| *end <= bytes.len())
+            .ok_or(InstructionError::MissingContent)?;
+
+        let control = decompress(
+            &bytes[BSDIFF_HEADER_LENGTH..control_end],
+            MAX_DECOMPRESSED_LENGTH,
+        )?;
+        let diff = decompress(&bytes[control_end..diff_end], target_length)?;
+        let extra = decompress(&bytes[diff_end..], target_length)?;
+
+        let mut builder = PatchBuilder::new();
+        let mut written = 0usize;
+        let mut source_position = 0i64;
+        let mut diff_position = 0usize;
+        let mut extra_position = 0usize;
+        for fields in control.chunks(24) {
+            if fields.len() < 24 {
+                return Err(InstructionError::MissingContent);
+            }
+            let diff_length = offset_from_bytes(&fields[0..8]);
+            let extra_length = offset_from_bytes(&fields[8..16]);
+            let seek = offset_from_bytes(&fields[16..24]);
+            if diff_length < 0 || extra_length < 0 {
+                return Err(InstructionError::InvalidContent);
+            }
+            let (diff_length, extra_length) = (diff_length as usize, extra_length as usize);
+            let end = written
+                .checked_add(diff_length)
+                .and_then(|end| end.checked_add(extra_length))
+                .filter(|end| *end <= target_length)
+                .ok_or(InstructionError::InvalidContent)?;
+
+            let difference = diff
+                .get(diff_position..diff_position + diff_length)
+                .ok_or(InstructionError::MissingContent)?;
+            match usize::try_from(source_position) {
+                Ok(start) => builder.copy_from(source, start, difference),
+                Err(_) => {
+                    let literal: Vec<u8> = difference
+                        .iter()
+                        .enumerate()
+                        .map(|(index, item)| {
+                            usize::try_from(source_position + index as i64)
+                                .ok()
+                                .and_then(|position| source.get(position))
+                                .map_or(*item, |source_item| source_item.wrapping_add(*item))
+                        })
+                        .collect();
+                    builder.add(&literal);
+                }
+            }
+            diff_position += diff_length;
+
+            let content = extra
+                .get(extra_position..extra_position + extra_length)
+                .ok_or(InstructionError::MissingContent)?;
+            builder.add(content);
+            extra_position += extra_length;
+
+            written = end;
+            source_position = source_position
+                .checked_add(diff_length as i64)
+                .and_then(|position| position.checked_add(seek))
+                .ok_or(InstructionError::InvalidContent)?;
+        }
+        if written != target_length {
+            return Err(InstructionError::MissingContent);
+        }
+        Ok(builder.finish(source.len()))
+    }
+}
+
+#[cfg(test)]
+mod bsdiff_tests {
+    use std::fs;
+
+    use super::*;
+
+    fn bspatch(patch: &[u8], source: &[u8]) -> Vec<u8> {
+        let control = decompress(
+            &patch[32..32 + offset_from_bytes(&patch[8..16]) as usize],
+            MAX_DECOMPRESSED_LENGTH,
+        )
+        .unwrap();
+        let diff_start = 32 + offset_from_bytes(&patch[8..16]) as usize;
+        let diff_end = diff_start + offset_from_bytes(&patch[16..24]) as usize;
+        let diff = decompress(&patch[diff_start..diff_end], MAX_DECOMPRESSED_LENGTH).unwrap();
+        let extra = decompress(&patch[diff_end..], MAX_DECOMPRESSED_LENGTH).unwrap();
+        let mut target = Vec::new();
+        let (mut source_position, mut diff_position, mut extra_position) = (0i64, 0, 0);
+        for fields in control.chunks(24) {
+            let diff_length = offset_from_bytes(&fields[0..8]) as usize;
+            let extra_length = offset_from_bytes(&fields[8..16]) as usize;
+            for index in 0..diff_length {
+                let source_item = source[(source_position as usize) + index];
+                target.push(source_item.wrapping_add(diff[diff_position + index]));
+            }
+            diff_position += diff_length;
+            target.extend_from_slice(&extra[extra_position..extra_position + extra_length]);
+            extra_position += extra_length;
+            source_position += diff_length as i64 + offset_from_bytes(&fields[16..24]);
+        }
+        assert_eq!(target.len() as i64, offset_from_bytes(&patch[24..32]));
+        target
+    }
+
+    #[test]
+    fn offset() {
+        for value in [0, 1, -1, 255, -256, i64::MAX, -i64::MAX] {
+            assert_eq!(offset_from_bytes(&offset_to_bytes(value)), value);
+        }
+        assert_eq!(offset_to_bytes(-1), [1, 0, 0, 0, 0, 0, 0, 0x80]);
+    }
+
+    #[test]
+    fn to_bsdiff() {
+        let source = fs::read("files/source.txt").unwrap();
+        let target = fs::read("files/target.txt").unwrap();
+        let patch = Patch::new(&source, &target);
+        assert_eq!(bspatch(&patch.to_bsdiff(), &source), target);
+        assert_eq!(bspatch(&Patch::new(b"AAA", b"").to_bsdiff(), b"AAA"), b"");
+    }
+
+    #[test]
+    fn from_bsdiff() {
+        let source = fs::read("files/source.txt").unwrap();
+        let target = fs::read("files/target.txt").unwrap();
+        let bytes = Patch::new(&source, &target).to_bsdiff();
+        let patch = Patch::from_bsdiff(&bytes, &source).unwrap();
+        assert_eq!(patch.apply(&source).unwrap(), target);
+    }
+
+    #[test]
+    fn to_bsdiff_offset_copy() {
+        let mut builder = PatchBuilder::new();
+        builder.copy(&[0, 0, 1]);
+        builder.add(b"-");
+        builder.copy_from(b"ABCD", 0, &[0, 0, 0]);
+        let patch = builder.finish(4);
+        assert_eq!(bspatch(&patch.to_bsdiff(), b"ABCD"), b"ABD-ABC");
+    }
+
+    #[test]
+    fn from_bsdiff_backward_seek() {
+        let mut control = Vec::new();
+        for field in [3, 1, -3, 3, 0, 0] {
+            control.extend(offset_to_bytes(field));
+        }
+        let mut bytes = BSDIFF_MAGIC.to_vec();
+        let control_block = compress(&control);
+        let diff_block = compress(&[0, 0, 1, 0, 0, 0]);
+        bytes.extend(offset_to_bytes(control_block.len() as i64));
+        bytes.extend(offset_to_bytes(diff_block.len() as i64));
+        bytes.extend(offset_to_bytes(7));
+        bytes.extend(control_block);
+        bytes.extend(diff_block);
+        bytes.extend(compress(b"-"));
+
+        let patch = Patch::from_bsdiff(&bytes, b"ABCD").unwrap();
+        assert_eq!(patch.apply(b"ABCD"), Some(b"ABD-ABC".to_vec()));
+        assert_eq!(bspatch(&bytes, b"ABCD"), b"ABD-ABC");
+    }
+
+    #[test]
+    fn from_bsdiff_err() {
+        assert_eq!(
+            Patch::from_bsdiff(b"BSDIFF41", b""),
+            Err(InstructionError::InvalidHeader)
+        );
+        let mut bytes = Patch::new(b"ABC", b"ABD").to_bsdiff();
+        bytes.truncate(bytes.len() - 1);
+        assert!(Patch::from_bsdiff(&bytes, b"ABC").is_err());
+    }
+
+    #[test]
+    fn from_bsdiff_crafted_control() {
+        for (fields, target_length) in [
+            ([0, 0, i64::MAX, 0, 0, 1], 0),
+            (
+                [i64::MAX, i64::MAX, 0, 0, 0, 0],
+                MAX_DECOMPRESSED_LENGTH as i64,
+            ),
+            ([0, 0, -i64::MAX, 0, 0, -2], 0),
+        ] {
+            let mut control = Vec::new();
+            for field in fields {
+                control.extend(offset_to_bytes(field));
+            }
+            let control_block = compress(&control);
+            let diff_block = compress(b"");
+            let mut bytes = BSDIFF_MAGIC.to_vec();
+            bytes.extend(offset_to_bytes(control_block.len() as i64));
+            bytes.extend(offset_to_bytes(diff_block.len() as i64));
+            bytes.extend(offset_to_bytes(target_length));
+            bytes.extend(control_block);
+            bytes.extend(diff_block);
+            bytes.extend(compress(b""));
+            assert_eq!(
+                Patch::from_bsdiff(&bytes, b"ABCD"),
+                Err(InstructionError::InvalidContent)
+            );
+        }
+    }
+
+    #[test]
+    fn from_bsdiff_limits() {
+        let control_block = compress(b"");
+        let diff_block = compress(b"");
+        let mut bytes = BSDIFF_MAGIC.to_vec();
+        bytes.extend(offset_to_bytes(control_block.len() as i64));
+        bytes.extend(offset_to_bytes(diff_block.len() as i64));
+        let header_length = bytes.len();
+        bytes.extend(offset_to_bytes(16));
+        bytes.extend(control_block);
+        bytes.extend(diff_block);
+        bytes.extend(compress(&[0; 4096]));
+        assert_eq!(
+            Patch::from_bsdiff(&bytes, b"ABCD"),
+            Err(InstructionError::InvalidCompression)
+        );
+
+        bytes[header_length..header_length + 8]
+            .copy_from_slice(&offset_to_bytes(MAX_DECOMPRESSED_LENGTH as i64 + 1));
+        assert_eq!(
+            Patch::from_bsdiff(&bytes, b"ABCD"),
+            Err(InstructionError::InvalidHeader)
+        );
+    }
+}
@@ -0,0 +1,201 @@
+use alloc::vec::Vec;
+use core::mem;
+
+use crate::{
+    instructions::{
+        add_instruction::AddInstruction, copy_instruction::CopyInstruction,
+        delta_instruction::DeltaInstruction, offset_copy_instruction::OffsetCopyInstruction,
+        remove_instruction::RemoveInstruction, InstructionContent, InstructionInfo,
+    },
+    patch::Patch,
+};
+
+#[derive(Debug, Default, PartialEq, Clone)]
+pub(crate) struct PatchBuilder {
+    instructions: Vec<DeltaInstruction>,
+    position: usize,
+}
+
+impl PatchBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn remove(&mut self, length: usize) {
+        self.position += length;
+        for _ in 0..length {
+            self.push(RemoveInstruction::default().into(), 0);
+        }
+    }
+
+    pub fn add(&mut self, content: &[u8]) {
+        for item in content.iter() {
+            self.push(AddInstruction::default().into(), *item);
+        }
+    }
+
+    pub fn copy(&mut self, difference: &[u8]) {
+        self.position += difference.len();
+        for item in difference.iter() {
+            self.push(CopyInstruction::default().into(), *item);
+        }
+    }
+
+    pub fn copy_offset(&mut self, offset: usize, length: usize) {
+        for offset in offset..offset + length {
+            match self.instructions.last_mut() {
+                Some(DeltaInstruction::OffsetCopy(last))
+                    if last.end() == Some(offset as u64) && !last.is_full() =>
+                {
+                    last.push(0).unwrap()
+                }
+                _ => self
+                    .instructions
+                    .push(OffsetCopyInstruction::new(offset as u64, 1).into()),
+            }
+        }
+    }
+
+    pub fn copy_from(&mut self, source: &[u8], offset: usize, difference: &[u8]) {
+        if offset + difference.len() > source.len() {
+            let literal: Vec<u8> = difference
+                .iter()
+                .enumerate()
+                .map(|(index, item)| {
+                    source
+                        .get(offset + index)
+                        .map_or(*item, |source_item| source_item.wrapping_add(*item))
+                })
+                .collect();
+            self.add(&literal);
+        } else if let Some(gap) = offset.checked_sub(self.position) {
+            self.remove(gap);
+            self.copy(difference);
+        } else {
+            for (index, item) in difference.iter().enumerate() {
+                match item {
+                    0 => self.copy_offset(offset + index, 1),
+                    _ => self.add(&[source[offset + index].wrapping_add(*item)]),
+                }
+            }
+        }
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn append(&mut self, patch: &Patch) {
+        let start = self.position;
+        for instruction in patch.instructions() {
+            match instruction {
+                DeltaInstruction::Remove(instruction) => self.remove(instruction.len() as usize),
+                DeltaInstruction::Add(instruction) => self.add(instruction.content()),
+                DeltaInstruction::Copy(instruction) => self.copy(instruction.content()),
+                DeltaInstruction::OffsetCopy(instruction) => self.copy_offset(
+                    start + instruction.offset() as usize,
+                    instruction.len() as usize,
+                ),
+            }
+        }
+    }
+
+    fn push(&mut self, empty: DeltaInstruction, item: u8) {
+        match self.instructions.last_mut() {
+            Some(last)
+                if mem::discriminant(last) == mem::discriminant(&empty) && !last.is_full() =>
+            {
+                last.push(item).unwrap()
+            }
+            _ => {
+                let mut instruction = empty;
+                instruction.push(item).unwrap();
+                self.instructions.push(instruction);
+            }
+        }
+    }
+
+    pub fn build(self) -> Patch {
+        Patch::from_instructions(self.instructions)
+    }
+
+    pub fn finish(mut self, source_length: usize) -> Patch {
+        self.remove(source_length.saturating_sub(self.position));
+        self.build()
+    }
+}
+
+#[cfg(test)]
+mod builder_tests {
+    use super::*;
+
+    #[test]
+    fn push() {
+        let mut builder = PatchBuilder::new();
+        builder.remove(300);
+        builder.add(b"AB");
+        builder.add(b"C");
+        builder.copy(&[0, 0, 1]);
+        assert_eq!(
+            builder.instructions,
+            vec![
+                RemoveInstruction::new(u8::MAX).into(),
+                RemoveInstruction::new(45).into(),
+                AddInstruction::new(b"ABC".to_vec()).into(),
+                CopyInstruction::new(vec![0, 0, 1]).into(),
+            ]
+        );
+    }
+
+    #[test]
+    fn build() {
+        let mut builder = PatchBuilder::new();
+        builder.remove(1);
+        builder.copy(&[0, 1]);
+        builder.add(b"D");
+        assert_eq!(builder.build().apply(b"ABC"), Some(b"BDD".to_vec()));
+    }
+
+    #[test]
+    fn copy_offset() {
+        let mut builder = PatchBuilder::new();
+        builder.copy_offset(3, 2);
+        builder.copy_offset(5, 300);
+        builder.copy_offset(0, 1);
+        assert_eq!(
+            builder.instructions,
+            vec![
+                OffsetCopyInstruction::new(3, u8::MAX).into(),
+                OffsetCopyInstruction::new(258, 47).into(),
+                OffsetCopyInstruction::new(0, 1).into(),
+            ]
+        );
+        assert_eq!(builder.position, 0);
+    }
+
+    #[test]
+    fn copy_from() {
+        let mut builder = PatchBuilder::new();
+        builder.copy_from(b"ABCDEF", 2, &[0, 0]);
+        builder.copy_from(b"ABCDEF", 0, &[0, 1]);
+        builder.copy_from(b"ABCDEF", 5, &[0, 0]);
+        assert_eq!(builder.position, 4);
+        assert_eq!(
+            builder.finish(6).apply(b"ABCDEF"),
+            Some(b"CDACF\0".to_vec())
+        );
+    }
+
+    #[test]
+    fn append() {
+        let source = b"ABCDEFGHIJKLMNOPQRSTUV";
+        let mut builder = PatchBuilder::new();
+        builder.remove(2);
+        builder.append(&Patch::new(&source[2..6], b"CDXF"));
+        assert_eq!(builder.position(), 6);
+        builder.append(&Patch::new_multi(&[&source[6..]], b"KLMNOPQRSTUV"));
+        assert_eq!(builder.position(), source.len());
+        let patch = builder.finish(source.len());
+        assert_eq!(patch.apply(source), Some(b"CDXFKLMNOPQRSTUV".to_vec()));
+    }
+}
@@ -0,0 +1,269 @@
+use alloc::{collections::BTreeMap, vec, vec::Vec};
+
+use crate::{builder::PatchBuilder, multi::Dictionary, patch::Patch};
+
+const GEAR: [u64; 256] = gear_table();
+const NORMALIZATION_BITS: u32 = 2;
+const FNV_OFFSET: u64 = 0xCBF2_9CE4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01B3;
+const CHUNKED_DIFF_CHUNKER: Chunker = Chunker::new(256, 1024, 4096);
+pub(crate) const MAX_LCS_CELLS: usize = 1 << 20;
+
+const fn gear_table() -> [u64; 256] {
+    let mut table = [0u64; 256];
+    let mut state = 0x9E37_79B9_7F4A_7C15u64;
+    let mut index = 0;
+    while index < 256 {
+        state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        table[index] = mix(state);
+        index += 1;
+    }
+    table
+}
+
+const fn high_mask(bits: u32) -> u64 {
+    match bits {
+        0 => 0,
+        64.. => u64::MAX,
+        _ => u64::MAX << (64 - bits),
+    }
+}
+
+pub(crate) const fn mix(mut value: u64) -> u64 {
+    value = (value ^ (value >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    value = (value ^ (value >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    value ^ (value >> 31)
+}
+
+pub(crate) fn chunk_hash(chunk: &[u8]) -> u64 {
+    chunk.iter().fold(FNV_OFFSET, |hash, item| {
+        (hash ^ *item as u64).wrapping_mul(FNV_PRIME)
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Chunker {
+    min_length: usize,
+    average_length: usize,
+    max_length: usize,
+    small_mask: u64,
+    large_mask: u64,
+}
+
+impl Chunker {
+    pub(crate) const fn new(min_length: usize, average_length: usize, max_length: usize) -> Self {
+        let bits = average_length.next_power_of_two().trailing_zeros();
+        Self {
+            min_length,
+            average_length,
+            max_length,
+            small_mask: high_mask(bits + NORMALIZATION_BITS),
+            large_mask: high_mask(bits.saturating_sub(NORMALIZATION_BITS)),
+        }
+    }
+
+    fn boundary(&self, bytes: &[u8]) -> usize {
+        if bytes.len() <= self.min_length {
+            return bytes.len();
+        }
+        let end = bytes.len().min(self.max_length);
+        let normal = end.min(self.average_length.max(self.min_length));
+        let mut hash = 0u64;
+        for index in self.min_length..end {
+            hash = (hash << 1).wrapping_add(GEAR[bytes[index] as usize]);
+            let mask = if index < normal {
+                self.small_mask
+            } else {
+                self.large_mask
+            };
+            if hash & mask == 0 {
+                return index + 1;
+            }
+        }
+        end
+    }
+
+    pub(crate) fn split<'a>(&self, bytes: &'a [u8]) -> Chunks<'a> {
+        Chunks {
+            chunker: *self,
+            bytes,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Chunks<'a> {
+    chunker: Chunker,
+    bytes: &'a [u8],
+}
+
+impl<'a> Iterator for Chunks<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.bytes.is_empty() {
+            return None;
+        }
+        let (chunk, rest) = self.bytes.split_at(self.chunker.boundary(self.bytes));
+        self.bytes = rest;
+        Some(chunk)
+    }
+}
+
+fn diff_region(
+    builder: &mut PatchBuilder,
+    dictionary: &Dictionary<'_>,
+    source: &[u8],
+    region_end: usize,
+    target: &[u8],
+) {
+    if target.is_empty() {
+        return;
+    }
+    let region = &source[builder.position()..region_end];
+    let comparable = region.len() >= target.len() / 2 && region.len() / 2 <= target.len();
+    if comparable && region.len().saturating_mul(target.len()) <= MAX_LCS_CELLS {
+        builder.append(&Patch::new(region, target));
+        return;
+    }
+    dictionary.diff_into(builder, target);
+}
+
+impl Patch {
+    pub fn new_chunked(source: &[u8], target: &[u8]) -> Self {
+        let mut offsets: BTreeMap<u64, Vec<usize>> = BTreeMap::new();
+        let mut offset = 0;
+        for chunk in CHUNKED_DIFF_CHUNKER.split(source) {
+            offsets.entry(chunk_hash(chunk)).or_default().push(offset);
+            offset += chunk.len();
+        }
+        let find = |chunk: &[u8], position: usize| {
+            let candidates = offsets.get(&chunk_hash(chunk))?;
+            let mut matching = candidates
+                .iter()
+                .copied()
+                .filter(|offset| source.get(*offset..*offset + chunk.len()) == Some(chunk));
+            let first = matching.clone().next()?;
+            Some(matching.find(|offset| *offset >= position).unwrap_or(first))
+        };
+
+        let sources = [source];
+        let dictionary = Dictionary::new(&sources);
+        let mut builder = PatchBuilder::new();
+        let mut unmatched_start = 0;
+        let mut target_offset = 0;
+        for chunk in CHUNKED_DIFF_CHUNKER.split(target) {
+            if let Some(offset) = find(chunk, builder.position()) {
+                let region_end = offset.max(builder.position());
+                diff_region(
+                    &mut builder,
+                    &dictionary,
+                    source,
+                    region_end,
+                    &target[unmatched_start..target_offset],
+                );
+                builder.copy_from(source, offset, &vec![0; chunk.len()]);
+                unmatched_start = target_offset + chunk.len();
+            }
+            target_offset += chunk.len();
+        }
+        let region_end = source.len().max(builder.position());
+        diff_region(
+            &mut builder,
+            &dictionary,
+            source,
+            region_end,
+            &target[unmatched_start..],
+        );
+        builder.finish(source.len())
+    }
+}
+
+#[cfg(test)]
+mod chunking_tests {
+    use std::fs;
+
+    use crate::instructions::{delta_instruction::DeltaInstruction, InstructionInfo};
+
+    use super::*;
+
+    #[test]
+    fn split() {
+        let chunker = Chunker::new(16, 64, 256);
+        let source = fs::read("files/source.txt").unwrap();
+        let chunks: Vec<&[u8]> = chunker.split(&source).collect();
+        assert_eq!(chunks.concat(), source);
+        assert!(chunks.len() > 1);
+        assert!(chunks.iter().all(|chunk| chunk.len() <= 256));
+        assert!(chunks[..chunks.len() - 1]
+            .iter()
+            .all(|chunk| chunk.len() > 16));
+
+        let mut shifted = b"inserted".to_vec();
+        shifted.extend_from_slice(&source);
+        let shifted_chunks: Vec<&[u8]> = chunker.split(&shifted).collect();
+        let shared = chunks
+            .iter()
+            .filter(|chunk| shifted_chunks.contains(chunk))
+            .count();
+        assert!(shared >= chunks.len() - 2);
+
+        assert_eq!(chunker.split(b"").count(), 0);
+        assert_eq!(chunker.split(b"short").collect::<Vec<_>>(), [b"short"]);
+        assert_eq!(
+            Chunker::new(0, 1 << 20, 4)
+                .split(&[7; 10])
+                .collect::<Vec<_>>(),
+            [&[7; 4][..], &[7; 4], &[7; 2]]
+        );
+    }
+
+    fn text(length: usize) -> Vec<u8> {
+        let words: [&[u8]; 8] = [
+            b"delta ", b"patch ", b"source ", b"target ", b"chunk ", b"copy ", b"add ", b"\n",
+        ];
+        let mut state = 1u64;
+        let mut text = Vec::new();
+        while text.len() < length {
+            state = mix(state);
+            text.extend_from_slice(words[(state % 8) as usize]);
+            text.extend_from_slice((state % 10_000).to_string().as_bytes());
+        }
+        text
+    }
+
+    #[test]
+    fn new_chunked() {
+        let source = text(60_000);
+        let mut target = source.clone();
+        target.splice(10_000..10_000, b"inserted paragraph\n".iter().copied());
+        target[30_000] = b'#';
+        target.drain(45_000..46_000);
+        target.extend_from_slice(&source[2_000..6_000]);
+
+        let patch = Patch::new_chunked(&source, &target);
+        assert_eq!(patch.apply(&source), Some(target.clone()));
+        let added: usize = patch
+            .instructions()
+            .iter()
+            .filter_map(|instruction| match instruction {
+                DeltaInstruction::Add(instruction) => Some(instruction.len() as usize),
+                _ => None,
+            })
+            .sum();
+        assert!(added < 100);
+        let decoded = Patch::try_from_bytes(&patch.to_bytes()).unwrap();
+        assert_eq!(decoded.apply(&source), Some(target));
+
+        let source = fs::read("files/source.txt").unwrap();
+        let target = fs::read("files/target.txt").unwrap();
+        let patch = Patch::new_chunked(&source, &target);
+        assert_eq!(patch.apply(&source), Some(target.clone()));
+        assert_eq!(Patch::new_chunked(b"", b"").apply(b""), Some(Vec::new()));
+        assert_eq!(
+            Patch::new_chunked(&source, b"").apply(&source),
+            Some(Vec::new())
+        );
+        assert_eq!(Patch::new_chunked(b"", &target).apply(b""), Some(target));
+    }
+}
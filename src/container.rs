@@ -0,0 +1,318 @@
+use alloc::vec::Vec;
+
+use crate::{
+    instructions::{
+        InstructionError, Result, ADD_INSTRUCTION_SIGN, COPY_INSTRUCTION_SIGN,
+        OFFSET_COPY_INSTRUCTION_SIGN, REMOVE_INSTRUCTION_SIGN,
+    },
+    patch::Patch,
+};
+
+pub(crate) const CONTAINER_MAGIC: &[u8; 4] = b"DLTZ";
+const CONTAINER_VERSION: u8 = 1;
+const CONTAINER_HEADER_LENGTH: usize = CONTAINER_MAGIC.len() + 2 + 3 * 8;
+const OFFSET_LENGTH: usize = core::mem::size_of::<u64>();
+pub(crate) const MAX_DECOMPRESSED_LENGTH: usize = 1 << 30;
+
+#[cfg(any(
+    feature = "bsdiff",
+    feature = "deflate",
+    feature = "zstd",
+    feature = "xz"
+))]
+pub(crate) fn read_limited(reader: impl std::io::Read, limit: usize) -> Result<Vec<u8>> {
+    use std::io::Read;
+    let mut decompressed = Vec::new();
+    reader
+        .take(limit as u64 + 1)
+        .read_to_end(&mut decompressed)
+        .map_err(|_| InstructionError::InvalidCompression)?;
+    if decompressed.len() > limit {
+        return Err(InstructionError::InvalidCompression);
+    }
+    Ok(decompressed)
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+#[non_exhaustive]
+pub enum Compression {
+    #[default]
+    None,
+    #[cfg(feature = "deflate")]
+    Deflate,
+    #[cfg(feature = "zstd")]
+    Zstd,
+    #[cfg(feature = "xz")]
+    Xz,
+}
+
+impl Compression {
+    pub(crate) fn byte_sign(&self) -> u8 {
+        match self {
+            Compression::None => 0,
+            #[cfg(feature = "deflate")]
+            Compression::Deflate => 1,
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => 2,
+            #[cfg(feature = "xz")]
+            Compression::Xz => 3,
+        }
+    }
+
+    pub(crate) fn try_from_byte_sign(sign: u8) -> Result<Self> {
+        match sign {
+            0 => Ok(Compression::None),
+            #[cfg(feature = "deflate")]
+            1 => Ok(Compression::Deflate),
+            #[cfg(feature = "zstd")]
+            2 => Ok(Compression::Zstd),
+            #[cfg(feature = "xz")]
+            3 => Ok(Compression::Xz),
+            _ => Err(InstructionError::UnsupportedCompression),
+        }
+    }
+
+    pub(crate) fn compress(&self, bytes: &[u8]) -> Vec<u8> {
+        match self {
+            Compression::None => bytes.to_vec(),
+            #[cfg(feature = "deflate")]
+            Compression::Deflate => {
+                use std::io::Write;
+                let mut encoder =
+                    flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::best());
+                encoder.write_all(bytes).unwrap();
+                encoder.finish().unwrap()
+            }
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => zstd::encode_all(bytes, zstd::DEFAULT_COMPRESSION_LEVEL).unwrap(),
+            #[cfg(feature = "xz")]
+            Compression::Xz => {
+                use std::io::Write;
+                let mut encoder = xz2::write::XzEncoder::new(Vec::new(), 9);
+                encoder.write_all(bytes).unwrap();
+                encoder.finish().unwrap()
+            }
+        }
+    }
+
+    pub(crate) fn decompress(&self, bytes: &[u8], limit: usize) -> Result<Vec<u8>> {
+        match self {
+            Compression::None => (bytes.len() <= limit)
+                .then(|| bytes.to_vec())
+                .ok_or(InstructionError::InvalidCompression),
+            #[cfg(feature = "deflate")]
+            Compression::Deflate => read_limited(flate2::read::DeflateDecoder::new(bytes), limit),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => read_limited(
+                zstd::stream::read::Decoder::new(bytes)
+                    .map_err(|_| InstructionError::InvalidCompression)?,
+                limit,
+            ),
+            #[cfg(feature = "xz")]
+            Compression::Xz => read_limited(xz2::read::XzDecoder::new(bytes), limit),
+        }
+    }
+}
+
+pub(crate) fn read_field<'a>(bytes: &mut &'a [u8]) -> Result<&'a [u8]> {
+    let (length, rest) = bytes
+        .split_first_chunk::<8>()
+        .ok_or(InstructionError::MissingLength)?;
+    let length = usize::try_from(u64::from_be_bytes(*length))
+        .ok()
+        .filter(|length| *length <= rest.len())
+        .ok_or(InstructionError::MissingContent)?;
+    let (field, rest) = rest.split_at(length);
+    *bytes = rest;
+    Ok(field)
+}
+
+pub(crate) fn is_container(bytes: &[u8]) -> bool {
+    bytes.starts_with(CONTAINER_MAGIC)
+}
+
+pub(crate) fn to_bytes(patch: &Patch, compression: Compression) -> Vec<u8> {
+    let mut control: Vec<u8> = Vec::new();
+    let mut diff: Vec<u8> = Vec::new();
+    let mut extra: Vec<u8> = Vec::new();
+    let raw = patch.to_bytes();
+    let mut index = 0;
+    while index < raw.len() {
+        let sign = raw[index];
+        let length = raw[index + 1] as usize;
+        control.extend_from_slice(&raw[index..index + 2]);
+        index += 2;
+        match sign {
+            ADD_INSTRUCTION_SIGN => extra.extend_from_slice(&raw[index..index + length]),
+            COPY_INSTRUCTION_SIGN => diff.extend_from_slice(&raw[index..index + length]),
+            OFFSET_COPY_INSTRUCTION_SIGN => {
+                control.extend_from_slice(&raw[index..index + OFFSET_LENGTH]);
+                index += OFFSET_LENGTH;
+                continue;
+            }
+            _ => continue,
+        }
+        index += length;
+    }
+
+    let streams = [
+        compression.compress(&control),
+        compression.compress(&diff),
+        compression.compress(&extra),
+    ];
+    let mut bytes: Vec<u8> =
+        Vec::with_capacity(CONTAINER_HEADER_LENGTH + streams.iter().map(Vec::len).sum::<usize>());
+    bytes.extend_from_slice(CONTAINER_MAGIC);
+    bytes.push(CONTAINER_VERSION);
+    bytes.push(compression.byte_sign());
+    for stream in streams.iter() {
+        bytes.extend((stream.len() as u64).to_be_bytes());
+    }
+    for stream in streams.iter() {
+        bytes.extend_from_slice(stream);
+    }
+    bytes
+}
+
+pub(crate) fn to_raw_bytes(bytes: &[u8]) -> Result<Vec<u8>> {
+    if bytes.len() < CONTAINER_HEADER_LENGTH || !is_container(bytes) {
+        return Err(InstructionError::InvalidHeader);
+    }
+    if bytes[CONTAINER_MAGIC.len()] != CONTAINER_VERSION {
+        return Err(InstructionError::InvalidHeader);
+    }
+    let compression = Compression::try_from_byte_sign(bytes[CONTAINER_MAGIC.len() + 1])?;
+
+    let mut offset = CONTAINER_HEADER_LENGTH;
+    let mut streams: Vec<Vec<u8>> = Vec::with_capacity(3);
+    for field in bytes[CONTAINER_MAGIC.len() + 2..CONTAINER_HEADER_LENGTH].chunks_exact(8) {
+        let length = u64::from_be_bytes(field.try_into().unwrap());
+        let end = usize::try_from(length)
+            .ok()
+            .and_then(|length| offset.checked_add(length))
+            .filter(|end| *end <= bytes.len())
+            .ok_or(InstructionError::MissingContent)?;
+        streams.push(compression.decompress(&bytes[offset..end], MAX_DECOMPRESSED_LENGTH)?);
+        offset = end;
+    }
+    if offset != bytes.len() {
+        return Err(InstructionError::InvalidContent);
+    }
+
+    let (control, diff, extra) = (&streams[0], &streams[1], &streams[2]);
+    let mut raw: Vec<u8> = Vec::with_capacity(control.len() + diff.len() + extra.len());
+    let mut control_iter = control.iter();
+    let mut diff_iter = diff.iter();
+    let mut extra_iter = extra.iter();
+    while let Some(sign) = control_iter.next() {
+        let length = *control_iter.next().ok_or(InstructionError::MissingLength)?;
+        raw.extend([*sign, length]);
+        let (content, length) = match *sign {
+            ADD_INSTRUCTION_SIGN => (&mut extra_iter, length as usize),
+            COPY_INSTRUCTION_SIGN => (&mut diff_iter, length as usize),
+            OFFSET_COPY_INSTRUCTION_SIGN => (&mut control_iter, OFFSET_LENGTH),
+            REMOVE_INSTRUCTION_SIGN => continue,
+            _ => return Err(InstructionError::InvalidSign),
+        };
+        let start = raw.len();
+        raw.extend(content.take(length));
+        if raw.len() - start < length {
+            return Err(InstructionError::MissingContent);
+        }
+    }
+    if diff_iter.len() != 0 || extra_iter.len() != 0 {
+        return Err(InstructionError::InvalidContent);
+    }
+    Ok(raw)
+}
+
+#[cfg(test)]
+mod container_tests {
+    use std::fs;
+
+    use crate::builder::PatchBuilder;
+
+    use super::*;
+
+    fn compressions() -> Vec<Compression> {
+        vec![
+            Compression::None,
+            #[cfg(feature = "deflate")]
+            Compression::Deflate,
+            #[cfg(feature = "zstd")]
+            Compression::Zstd,
+            #[cfg(feature = "xz")]
+            Compression::Xz,
+        ]
+    }
+
+    #[test]
+    fn streams() {
+        let patch = Patch::new(b"ABCDEFGH", b"XXABCDEFH");
+        let bytes = to_bytes(&patch, Compression::None);
+        assert!(is_container(&bytes));
+        assert_eq!(to_raw_bytes(&bytes).unwrap(), patch.to_bytes());
+
+        let mut builder = PatchBuilder::new();
+        builder.copy(&[0, 1]);
+        builder.copy_offset(0, 2);
+        builder.add(b"Z");
+        let patch = builder.finish(4);
+        let bytes = to_bytes(&patch, Compression::None);
+        assert_eq!(to_raw_bytes(&bytes).unwrap(), patch.to_bytes());
+    }
+
+    #[test]
+    fn round_trip() {
+        let source = fs::read("files/source.txt").unwrap();
+        let target = fs::read("files/target.txt").unwrap();
+        let patch = Patch::new(&source, &target);
+        for compression in compressions() {
+            let bytes = patch.to_compressed_bytes(compression);
+            assert_eq!(Patch::try_from_bytes(&bytes).unwrap(), patch);
+        }
+    }
+
+    #[test]
+    fn to_raw_bytes_err() {
+        let patch = Patch::new(b"AAAABBBB", b"AACCBBBBDD");
+        let bytes = to_bytes(&patch, Compression::None);
+
+        assert_eq!(
+            to_raw_bytes(&bytes[..CONTAINER_HEADER_LENGTH - 1]),
+            Err(InstructionError::InvalidHeader)
+        );
+
+        let mut unknown_compression = bytes.clone();
+        unknown_compression[CONTAINER_MAGIC.len() + 1] = u8::MAX;
+        assert_eq!(
+            to_raw_bytes(&unknown_compression),
+            Err(InstructionError::UnsupportedCompression)
+        );
+
+        assert_eq!(
+            to_raw_bytes(&bytes[..bytes.len() - 1]),
+            Err(InstructionError::MissingContent)
+        );
+
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert_eq!(
+            to_raw_bytes(&trailing),
+            Err(InstructionError::InvalidContent)
+        );
+    }
+
+    #[test]
+    fn decompress_limit() {
+        let bytes = vec![0u8; 4096];
+        for compression in compressions() {
+            let compressed = compression.compress(&bytes);
+            assert_eq!(compression.decompress(&compressed, 4096), Ok(bytes.clone()));
+            assert_eq!(
+                compression.decompress(&compressed, 4095),
+                Err(InstructionError::InvalidCompression)
+            );
+        }
+    }
+}
@@ -0,0 +1,259 @@
+use std::{
+    ffi::OsString,
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::{crc32::crc32, instructions::InstructionError, patch::Patch};
+
+const BACKUP_EXTENSION: &str = "bak";
+const TEMPORARY_EXTENSION: &str = "deltas-tmp";
+
+static TEMPORARY_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+fn sibling_path(path: &Path, extension: &str) -> PathBuf {
+    let mut name = path.file_name().map(OsString::from).unwrap_or_default();
+    name.push(".");
+    name.push(extension);
+    path.with_file_name(name)
+}
+
+pub(crate) fn temporary_path(path: &Path) -> PathBuf {
+    let counter = TEMPORARY_COUNTER.fetch_add(1, Ordering::Relaxed);
+    sibling_path(
+        path,
+        &format!("{}-{}.{}", process::id(), counter, TEMPORARY_EXTENSION),
+    )
+}
+
+fn sync_parent(path: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    if let Some(parent) = path.parent() {
+        let parent = if parent.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            parent
+        };
+        File::open(parent)?.sync_all()?;
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
+
+fn write_synced(path: &Path, bytes: &[u8], permissions: fs::Permissions) -> io::Result<()> {
+    let mut file = OpenOptions::new().write(true).create_new(true).open(path)?;
+    file.write_all(bytes)?;
+    file.set_permissions(permissions)?;
+    file.sync_all()
+}
+
+fn replace(temporary: &Path, path: &Path) -> io::Result<()> {
+    fs::rename(temporary, path)?;
+    sync_parent(path)
+}
+
+struct TemporaryFile(Option<PathBuf>);
+
+impl TemporaryFile {
+    fn new(path: &Path) -> Self {
+        Self(Some(temporary_path(path)))
+    }
+
+    fn path(&self) -> &Path {
+        self.0.as_deref().unwrap()
+    }
+
+    fn persist(mut self, path: &Path) -> io::Result<()> {
+        replace(self.path(), path)?;
+        self.0 = None;
+        Ok(())
+    }
+}
+
+impl Drop for TemporaryFile {
+    fn drop(&mut self) {
+        if let Some(path) = self.0.take() {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+pub fn backup_path(path: impl AsRef<Path>) -> PathBuf {
+    sibling_path(path.as_ref(), BACKUP_EXTENSION)
+}
+
+pub fn rollback(path: impl AsRef<Path>) -> io::Result<()> {
+    let path = path.as_ref();
+    replace(&backup_path(path), path)
+}
+
+#[derive(Debug, Clone)]
+pub struct FilePatch<'a> {
+    patch: &'a Patch,
+    target_checksum: Option<u32>,
+    backup: bool,
+}
+
+impl<'a> FilePatch<'a> {
+    pub fn new(patch: &'a Patch) -> Self {
+        Self {
+            patch,
+            target_checksum: None,
+            backup: false,
+        }
+    }
+
+    pub fn target_checksum(mut self, checksum: u32) -> Self {
+        self.target_checksum = Some(checksum);
+        self
+    }
+
+    pub fn backup(mut self, backup: bool) -> Self {
+        self.backup = backup;
+        self
+    }
+
+    pub fn apply(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let source = fs::read(path)?;
+        let permissions = fs::metadata(path)?.permissions();
+        let target = self
+            .patch
+            .apply(&source)
+            .ok_or(InstructionError::SourceMismatch)?;
+        let checksum = crc32(&target);
+        if self
+            .target_checksum
+            .is_some_and(|expected| expected != checksum)
+        {
+            return Err(InstructionError::InvalidChecksum.into());
+        }
+
+        let temporary = TemporaryFile::new(path);
+        write_synced(temporary.path(), &target, permissions.clone())?;
+        if crc32(&fs::read(temporary.path())?) != checksum {
+            return Err(InstructionError::InvalidChecksum.into());
+        }
+
+        if self.backup {
+            let backup = backup_path(path);
+            let backup_temporary = TemporaryFile::new(&backup);
+            write_synced(backup_temporary.path(), &source, permissions)?;
+            backup_temporary.persist(&backup)?;
+        }
+        temporary.persist(path)
+    }
+}
+
+impl Patch {
+    pub fn apply_to_file(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        FilePatch::new(self).apply(path)
+    }
+}
+
+#[cfg(test)]
+mod file_tests {
+    use std::env;
+
+    use super::*;
+
+    fn temporary_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("deltas_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn sibling() {
+        assert_eq!(
+            backup_path("dir/file.txt"),
+            PathBuf::from("dir/file.txt.bak")
+        );
+        assert_eq!(
+            sibling_path(Path::new("file"), TEMPORARY_EXTENSION),
+            PathBuf::from("file.deltas-tmp")
+        );
+        let temporary = temporary_path(Path::new("dir/file"));
+        assert_ne!(temporary, temporary_path(Path::new("dir/file")));
+        assert_eq!(temporary.parent(), Some(Path::new("dir")));
+        let name = temporary
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .into_owned();
+        assert!(name.starts_with(&format!("file.{}-", process::id())));
+        assert!(name.ends_with(TEMPORARY_EXTENSION));
+    }
+
+    #[test]
+    fn apply_and_rollback() {
+        let source = fs::read("files/source.txt").unwrap();
+        let target = fs::read("files/target.txt").unwrap();
+        let dir = temporary_dir("apply_and_rollback");
+        let path = dir.join("file.txt");
+        fs::write(&path, &source).unwrap();
+
+        let patch = Patch::new(&source, &target);
+        FilePatch::new(&patch)
+            .target_checksum(crc32(&target))
+            .backup(true)
+            .apply(&path)
+            .unwrap();
+        assert_eq!(fs::read(&path).unwrap(), target);
+        assert_eq!(fs::read(backup_path(&path)).unwrap(), source);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+
+        rollback(&path).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), source);
+        assert_eq!(rollback(&path).unwrap_err().kind(), io::ErrorKind::NotFound);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn apply_err() {
+        let dir = temporary_dir("apply_err");
+        let path = dir.join("file.txt");
+        fs::write(&path, b"ABC").unwrap();
+
+        let patch = Patch::new(b"ABC", b"ABD");
+        let error = FilePatch::new(&patch)
+            .target_checksum(0)
+            .apply(&path)
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(
+            error.into_inner().unwrap().to_string(),
+            InstructionError::InvalidChecksum.to_string()
+        );
+        assert_eq!(fs::read(&path).unwrap(), b"ABC");
+
+        let patch = Patch::new(b"ABCD", b"ABD");
+        assert_eq!(
+            patch.apply_to_file(&path).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+        assert_eq!(fs::read(&path).unwrap(), b"ABC");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn apply_backup_err() {
+        let dir = temporary_dir("apply_backup_err");
+        let path = dir.join("file.txt");
+        fs::write(&path, b"ABC").unwrap();
+        fs::create_dir(backup_path(&path)).unwrap();
+
+        let patch = Patch::new(b"ABC", b"ABD");
+        assert!(FilePatch::new(&patch).backup(true).apply(&path).is_err());
+        assert_eq!(fs::read(&path).unwrap(), b"ABC");
+        assert!(backup_path(&path).is_dir());
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,222 @@
+use alloc::{vec, vec::Vec};
+
+use crate::{
+    builder::PatchBuilder,
+    instructions::{InstructionError, Result},
+    operation::Operation,
+    patch::Patch,
+};
+
+const MIN_COPY_LENGTH: usize = 4;
+const MAX_COPY_LENGTH: usize = 0x10000;
+const MAX_INSERT_LENGTH: usize = 0x7F;
+
+fn write_size(bytes: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        bytes.push(0x80 | (value & 0x7F) as u8);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+fn read_size(bytes: &mut core::slice::Iter<'_, u8>) -> Result<usize> {
+    let mut value = 0usize;
+    let mut shift = 0;
+    loop {
+        let byte = *bytes.next().ok_or(InstructionError::MissingLength)?;
+        let bits = (byte & 0x7F) as usize;
+        let shifted = bits
+            .checked_shl(shift)
+            .filter(|shifted| shifted >> shift == bits)
+            .ok_or(InstructionError::InvalidLength)?;
+        value |= shifted;
+        shift += 7;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+}
+
+fn write_copy(bytes: &mut Vec<u8>, offset: usize, length: usize) {
+    let command = bytes.len();
+    bytes.push(0x80);
+    for index in 0..4 {
+        let byte = (offset >> (8 * index)) as u8;
+        if byte != 0 {
+            bytes[command] |= 1 << index;
+            bytes.push(byte);
+        }
+    }
+    let length = if length == MAX_COPY_LENGTH { 0 } else { length };
+    for index in 0..3 {
+        let byte = (length >> (8 * index)) as u8;
+        if byte != 0 {
+            bytes[command] |= 1 << (4 + index);
+            bytes.push(byte);
+        }
+    }
+}
+
+fn write_insert(bytes: &mut Vec<u8>, content: &[u8]) {
+    for chunk in content.chunks(MAX_INSERT_LENGTH) {
+        bytes.push(chunk.len() as u8);
+        bytes.extend_from_slice(chunk);
+    }
+}
+
+impl Patch {
+    pub fn to_git_delta(&self, source: &[u8]) -> Option<Vec<u8>> {
+        let operations = self.operations(source, MIN_COPY_LENGTH)?;
+        let mut bytes: Vec<u8> = Vec::new();
+        write_size(&mut bytes, source.len());
+        write_size(&mut bytes, self.target_length());
+        for operation in operations.iter() {
+            match operation {
+                Operation::Add(content) => write_insert(&mut bytes, content),
+                Operation::Copy { offset, length } if offset + length > u32::MAX as usize => {
+                    write_insert(&mut bytes, &source[*offset..offset + length])
+                }
+                Operation::Copy { offset, length } => {
+                    let mut start = 0;
+                    while start < *length {
+                        let chunk = (length - start).min(MAX_COPY_LENGTH);
+                        write_copy(&mut bytes, offset + start, chunk);
+                        start += chunk;
+                    }
+                }
+            }
+        }
+        Some(bytes)
+    }
+
+    pub fn from_git_delta(bytes: &[u8], source: &[u8]) -> Result<Self> {
+        let mut bytes = bytes.iter();
+        if read_size(&mut bytes)? != source.len() {
+            return Err(InstructionError::SourceMismatch);
+        }
+        let target_length = read_size(&mut bytes)?;
+
+        let mut builder = PatchBuilder::new();
+        let mut written = 0usize;
+        while let Some(command) = bytes.next() {
+            if command & 0x80 != 0 {
+                let mut offset = 0usize;
+                for index in 0..4 {
+                    if command & (1 << index) != 0 {
+                        let byte = *bytes.next().ok_or(InstructionError::MissingContent)?;
+                        offset |= (byte as usize) << (8 * index);
+                    }
+                }
+                let mut length = 0usize;
+                for index in 0..3 {
+                    if command & (1 << (4 + index)) != 0 {
+                        let byte = *bytes.next().ok_or(InstructionError::MissingContent)?;
+                        length |= (byte as usize) << (8 * index);
+                    }
+                }
+                if length == 0 {
+                    length = MAX_COPY_LENGTH;
+                }
+                if offset + length > source.len() {
+                    return Err(InstructionError::InvalidContent);
+                }
+                builder.copy_from(source, offset, &vec![0; length]);
+                written += length;
+            } else if *command != 0 {
+                let content = bytes.as_slice();
+                let length = *command as usize;
+                if content.len() < length {
+                    return Err(InstructionError::MissingContent);
+                }
+                builder.add(&content[..length]);
+                bytes.nth(length - 1);
+                written += length;
+            } else {
+                return Err(InstructionError::InvalidSign);
+            }
+            if written > target_length {
+                return Err(InstructionError::InvalidContent);
+            }
+        }
+        if written != target_length {
+            return Err(InstructionError::MissingContent);
+        }
+        Ok(builder.finish(source.len()))
+    }
+}
+
+#[cfg(test)]
+mod git_delta_tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn size() {
+        let mut bytes = Vec::new();
+        write_size(&mut bytes, 300);
+        assert_eq!(bytes, [0xAC, 0x02]);
+        assert_eq!(read_size(&mut bytes.iter()), Ok(300));
+        assert_eq!(
+            read_size(&mut [0xFF; 11].iter()),
+            Err(InstructionError::InvalidLength)
+        );
+
+        let mut bytes = Vec::new();
+        write_size(&mut bytes, usize::MAX);
+        assert_eq!(read_size(&mut bytes.iter()), Ok(usize::MAX));
+        *bytes.last_mut().unwrap() += 1;
+        assert_eq!(
+            read_size(&mut bytes.iter()),
+            Err(InstructionError::InvalidLength)
+        );
+    }
+
+    #[test]
+    fn copy() {
+        let mut bytes = Vec::new();
+        write_copy(&mut bytes, 0x010200, MAX_COPY_LENGTH);
+        assert_eq!(bytes, [0x80 | 0b0110, 0x02, 0x01]);
+        bytes.clear();
+        write_copy(&mut bytes, 0, 0x0300);
+        assert_eq!(bytes, [0x80 | 0b0010_0000, 0x03]);
+    }
+
+    #[test]
+    fn round_trip() {
+        let source = fs::read("files/source.txt").unwrap();
+        let target = fs::read("files/target.txt").unwrap();
+        let bytes = Patch::new(&source, &target).to_git_delta(&source).unwrap();
+        let patch = Patch::from_git_delta(&bytes, &source).unwrap();
+        assert_eq!(patch.apply(&source).unwrap(), target);
+    }
+
+    #[test]
+    fn from_git_delta_offset_copy() {
+        // Copy "CDEF", insert "-", then copy "ABCD" from before the current source position.
+        let bytes = [6, 9, 0x91, 2, 4, 1, b'-', 0x90, 4];
+        let patch = Patch::from_git_delta(&bytes, b"ABCDEF").unwrap();
+        assert_eq!(patch.apply(b"ABCDEF").unwrap(), b"CDEF-ABCD");
+        assert_eq!(patch.to_git_delta(b"ABCDEF").unwrap(), bytes);
+    }
+
+    #[test]
+    fn from_git_delta_err() {
+        assert_eq!(
+            Patch::from_git_delta(&[5, 0], b"ABCDEF"),
+            Err(InstructionError::SourceMismatch)
+        );
+        assert_eq!(
+            Patch::from_git_delta(&[6, 1, 0], b"ABCDEF"),
+            Err(InstructionError::InvalidSign)
+        );
+        assert_eq!(
+            Patch::from_git_delta(&[6, 4, 0x91, 4, 4], b"ABCDEF"),
+            Err(InstructionError::InvalidContent)
+        );
+        assert_eq!(
+            Patch::from_git_delta(&[6, 4, 3, b'A'], b"ABCDEF"),
+            Err(InstructionError::MissingContent)
+        );
+    }
+}
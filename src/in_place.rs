@@ -0,0 +1,208 @@
+#[cfg(feature = "std")]
+use std::{
+    fs::OpenOptions,
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+use alloc::vec::Vec;
+
+use crate::{
+    instructions::{
+        delta_instruction::DeltaInstruction, InstructionError, InstructionInfo, Result,
+    },
+    patch::Patch,
+};
+
+#[cfg(feature = "std")]
+const MOVE_CHUNK_LENGTH: usize = 1 << 16;
+
+impl Patch {
+    fn in_place_shift(&self, source_length: usize) -> Result<usize> {
+        if source_length != self.source_lenth() || !self.offsets_fit(source_length) {
+            return Err(InstructionError::SourceMismatch);
+        }
+        // Every write has to end before the lowest source byte any later instruction still reads.
+        let mut shift = 0usize;
+        let mut next_read = usize::MAX;
+        let mut position = source_length;
+        let mut written = self.target_length();
+        for instruction in self.instructions().iter().rev() {
+            if next_read != usize::MAX {
+                shift = shift.max(written.saturating_sub(next_read));
+            }
+            let length = instruction.len() as usize;
+            match instruction {
+                DeltaInstruction::Remove(_) => position -= length,
+                DeltaInstruction::Add(_) => written -= length,
+                DeltaInstruction::Copy(_) => {
+                    position -= length;
+                    written -= length;
+                    next_read = next_read.min(position);
+                }
+                DeltaInstruction::OffsetCopy(instruction) => {
+                    written -= length;
+                    next_read = next_read.min(instruction.offset() as usize);
+                }
+            }
+        }
+        Ok(shift)
+    }
+
+    pub fn apply_in_place(&self, buffer: &mut Vec<u8>) -> Result<()> {
+        let source_length = buffer.len();
+        let shift = self.in_place_shift(source_length)?;
+        let target_length = self.target_length();
+        buffer.resize((source_length + shift).max(target_length), 0);
+        buffer.copy_within(0..source_length, shift);
+
+        let mut position = 0usize;
+        let mut written = 0usize;
+        for instruction in self.instructions() {
+            match instruction {
+                DeltaInstruction::Remove(instruction) => position += instruction.len() as usize,
+                DeltaInstruction::Add(instruction) => {
+                    let content = instruction.content();
+                    buffer[written..written + content.len()].copy_from_slice(content);
+                    written += content.len();
+                }
+                DeltaInstruction::Copy(instruction) => {
+                    let content = instruction.content();
+                    let start = shift + position;
+                    buffer.copy_within(start..start + content.len(), written);
+                    for (item, difference) in buffer[written..].iter_mut().zip(content.iter()) {
+                        *item = item.wrapping_add(*difference);
+                    }
+                    position += content.len();
+                    written += content.len();
+                }
+                DeltaInstruction::OffsetCopy(instruction) => {
+                    let start = shift + instruction.offset() as usize;
+                    let length = instruction.len() as usize;
+                    buffer.copy_within(start..start + length, written);
+                    written += length;
+                }
+            }
+        }
+        buffer.truncate(target_length);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl Patch {
+    pub fn apply_in_place_file(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+        let source_length = file.metadata()?.len() as usize;
+        let shift = self.in_place_shift(source_length)?;
+        let target_length = self.target_length();
+        file.set_len((source_length + shift).max(target_length) as u64)?;
+
+        let mut chunk: Vec<u8> = vec![0; MOVE_CHUNK_LENGTH.min(source_length)];
+        let mut end = source_length;
+        while shift > 0 && end > 0 {
+            let start = end.saturating_sub(MOVE_CHUNK_LENGTH);
+            let chunk = &mut chunk[..end - start];
+            file.seek(SeekFrom::Start(start as u64))?;
+            file.read_exact(chunk)?;
+            file.seek(SeekFrom::Start((start + shift) as u64))?;
+            file.write_all(chunk)?;
+            end = start;
+        }
+
+        let mut content: Vec<u8> = Vec::with_capacity(u8::MAX as usize);
+        let mut position = 0usize;
+        let mut written = 0usize;
+        for instruction in self.instructions() {
+            content.clear();
+            match instruction {
+                DeltaInstruction::Remove(instruction) => {
+                    position += instruction.len() as usize;
+                    continue;
+                }
+                DeltaInstruction::Add(instruction) => content.extend(instruction.content()),
+                DeltaInstruction::Copy(instruction) => {
+                    content.resize(instruction.len() as usize, 0);
+                    file.seek(SeekFrom::Start((shift + position) as u64))?;
+                    file.read_exact(&mut content)?;
+                    for (item, difference) in content.iter_mut().zip(instruction.content()) {
+                        *item = item.wrapping_add(*difference);
+                    }
+                    position += content.len();
+                }
+                DeltaInstruction::OffsetCopy(instruction) => {
+                    content.resize(instruction.len() as usize, 0);
+                    file.seek(SeekFrom::Start(shift as u64 + instruction.offset()))?;
+                    file.read_exact(&mut content)?;
+                }
+            }
+            file.seek(SeekFrom::Start(written as u64))?;
+            file.write_all(&content)?;
+            written += content.len();
+        }
+        file.set_len(target_length as u64)?;
+        file.sync_all()
+    }
+}
+
+#[cfg(test)]
+mod in_place_tests {
+    use std::fs;
+
+    use super::*;
+    use crate::instructions::{
+        add_instruction::AddInstruction, copy_instruction::CopyInstruction,
+        offset_copy_instruction::OffsetCopyInstruction, remove_instruction::RemoveInstruction,
+    };
+
+    #[test]
+    fn apply_in_place() {
+        let source = fs::read("files/source.txt").unwrap();
+        let target = fs::read("files/target.txt").unwrap();
+        for (source, target) in [(&source, &target), (&target, &source)] {
+            let patch = Patch::new(source, target);
+            let mut buffer = source.clone();
+            patch.apply_in_place(&mut buffer).unwrap();
+            assert_eq!(&buffer, target);
+        }
+    }
+
+    #[test]
+    fn apply_in_place_shift() {
+        let patch = Patch::from_instructions(vec![
+            AddInstruction::new(b"XYZ".to_vec()).into(),
+            CopyInstruction::new(vec![0, 1]).into(),
+            RemoveInstruction::new(1).into(),
+            OffsetCopyInstruction::new(0, 3).into(),
+        ]);
+        assert_eq!(patch.in_place_shift(3), Ok(5));
+        let mut buffer = b"ABC".to_vec();
+        patch.apply_in_place(&mut buffer).unwrap();
+        assert_eq!(buffer, b"XYZACABC");
+        assert_eq!(patch.apply(b"ABC").unwrap(), buffer);
+
+        let mut buffer = b"AB".to_vec();
+        assert_eq!(
+            patch.apply_in_place(&mut buffer),
+            Err(InstructionError::SourceMismatch)
+        );
+        assert_eq!(buffer, b"AB");
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn apply_in_place_file() {
+        let source = fs::read("files/source.txt").unwrap();
+        let target = fs::read("files/target.txt").unwrap();
+        let path = std::env::temp_dir().join(format!("deltas_in_place_{}", std::process::id()));
+        fs::write(&path, &source).unwrap();
+        let patch = Patch::new(&source, &target);
+        patch.apply_in_place_file(&path).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), target);
+        assert_eq!(
+            patch.apply_in_place_file(&path).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,4 +1,5 @@
-use std::{iter::Peekable, slice::Iter};
+use alloc::vec::Vec;
+use core::{iter::Peekable, slice::Iter};
 
 use super::{
     InstructionBytes, InstructionContent, InstructionError, InstructionInfo, Result,
@@ -18,6 +19,10 @@ impl AddInstruction {
         );
         Self { content }
     }
+
+    pub fn content(&self) -> &[u8] {
+        &self.content
+    }
 }
 
 impl InstructionInfo for AddInstruction {
@@ -58,7 +63,7 @@ impl InstructionContent for AddInstruction {
         }
     }
 
-    fn apply(&self, _: &mut Iter<'_, u8>, target: &mut Vec<u8>) {
+    fn apply(&self, _: &[u8], _: &mut usize, target: &mut Vec<u8>) {
         target.extend(self.content.iter());
     }
 }
@@ -121,7 +126,7 @@ impl From<AddInstruction> for Vec<u8> {
 impl TryFrom<&mut Peekable<Iter<'_, u8>>> for AddInstruction {
     type Error = InstructionError;
 
-    fn try_from(value: &mut Peekable<Iter<'_, u8>>) -> std::result::Result<Self, Self::Error> {
+    fn try_from(value: &mut Peekable<Iter<'_, u8>>) -> core::result::Result<Self, Self::Error> {
         AddInstruction::try_from_bytes(value)
     }
 }
@@ -129,7 +134,7 @@ impl TryFrom<&mut Peekable<Iter<'_, u8>>> for AddInstruction {
 impl TryFrom<Peekable<Iter<'_, u8>>> for AddInstruction {
     type Error = InstructionError;
 
-    fn try_from(mut value: Peekable<Iter<'_, u8>>) -> std::result::Result<Self, Self::Error> {
+    fn try_from(mut value: Peekable<Iter<'_, u8>>) -> core::result::Result<Self, Self::Error> {
         AddInstruction::try_from_bytes(&mut value)
     }
 }
@@ -137,7 +142,7 @@ impl TryFrom<Peekable<Iter<'_, u8>>> for AddInstruction {
 impl TryFrom<Vec<u8>> for AddInstruction {
     type Error = InstructionError;
 
-    fn try_from(value: Vec<u8>) -> std::result::Result<Self, Self::Error> {
+    fn try_from(value: Vec<u8>) -> core::result::Result<Self, Self::Error> {
         AddInstruction::try_from_bytes(&mut value.iter().peekable())
     }
 }
@@ -145,7 +150,7 @@ impl TryFrom<Vec<u8>> for AddInstruction {
 impl TryFrom<&[u8]> for AddInstruction {
     type Error = InstructionError;
 
-    fn try_from(value: &[u8]) -> std::result::Result<Self, Self::Error> {
+    fn try_from(value: &[u8]) -> core::result::Result<Self, Self::Error> {
         AddInstruction::try_from_bytes(&mut value.iter().peekable())
     }
 }
@@ -1,4 +1,5 @@
-use std::{iter::Peekable, slice::Iter};
+use alloc::vec::Vec;
+use core::{iter::Peekable, slice::Iter};
 
 use super::{
     InstructionBytes, InstructionContent, InstructionError, InstructionInfo, Result,
@@ -18,6 +19,10 @@ impl CopyInstruction {
         );
         Self { content }
     }
+
+    pub fn content(&self) -> &[u8] {
+        &self.content
+    }
 }
 
 impl InstructionInfo for CopyInstruction {
@@ -65,10 +70,11 @@ impl InstructionContent for CopyInstruction {
         }
     }
 
-    fn apply(&self, source: &mut Iter<'_, u8>, target: &mut Vec<u8>) {
-        for item in self.content.iter() {
-            target.push(source.next().unwrap().wrapping_add(*item));
+    fn apply(&self, source: &[u8], position: &mut usize, target: &mut Vec<u8>) {
+        for (item, source_item) in self.content.iter().zip(source[*position..].iter()) {
+            target.push(source_item.wrapping_add(*item));
         }
+        *position += self.content.len();
     }
 }
 
@@ -134,7 +140,7 @@ impl From<CopyInstruction> for Vec<u8> {
 impl TryFrom<&mut Peekable<Iter<'_, u8>>> for CopyInstruction {
     type Error = InstructionError;
 
-    fn try_from(value: &mut Peekable<Iter<'_, u8>>) -> std::result::Result<Self, Self::Error> {
+    fn try_from(value: &mut Peekable<Iter<'_, u8>>) -> core::result::Result<Self, Self::Error> {
         CopyInstruction::try_from_bytes(value)
     }
 }
@@ -142,7 +148,7 @@ impl TryFrom<&mut Peekable<Iter<'_, u8>>> for CopyInstruction {
 impl TryFrom<Peekable<Iter<'_, u8>>> for CopyInstruction {
     type Error = InstructionError;
 
-    fn try_from(mut value: Peekable<Iter<'_, u8>>) -> std::result::Result<Self, Self::Error> {
+    fn try_from(mut value: Peekable<Iter<'_, u8>>) -> core::result::Result<Self, Self::Error> {
         CopyInstruction::try_from_bytes(&mut value)
     }
 }
@@ -150,7 +156,7 @@ impl TryFrom<Peekable<Iter<'_, u8>>> for CopyInstruction {
 impl TryFrom<Vec<u8>> for CopyInstruction {
     type Error = InstructionError;
 
-    fn try_from(value: Vec<u8>) -> std::result::Result<Self, Self::Error> {
+    fn try_from(value: Vec<u8>) -> core::result::Result<Self, Self::Error> {
         CopyInstruction::try_from_bytes(&mut value.iter().peekable())
     }
 }
@@ -158,7 +164,7 @@ impl TryFrom<Vec<u8>> for CopyInstruction {
 impl TryFrom<&[u8]> for CopyInstruction {
     type Error = InstructionError;
 
-    fn try_from(value: &[u8]) -> std::result::Result<Self, Self::Error> {
+    fn try_from(value: &[u8]) -> core::result::Result<Self, Self::Error> {
         CopyInstruction::try_from_bytes(&mut value.iter().peekable())
     }
 }
@@ -171,7 +177,7 @@ mod copy_instruction_tests {
 
     #[test]
     fn instruction_info() {
-        let mut instruction = CopyInstruction::new(vec![0; u8::MAX.into()]);
+        let mut instruction = CopyInstruction::new(vec![0; u8::MAX.try_into().unwrap()]);
         assert_eq!(instruction.len(), u8::MAX);
         assert!(instruction.is_full());
 
@@ -1,9 +1,12 @@
-use std::{iter::Peekable, slice::Iter};
+use alloc::vec::Vec;
+use core::{iter::Peekable, slice::Iter};
 
 use super::{
     add_instruction::AddInstruction, copy_instruction::CopyInstruction,
-    remove_instruction::RemoveInstruction, InstructionBytes, InstructionContent, InstructionError,
-    InstructionInfo, Result, ADD_INSTRUCTION_SIGN, COPY_INSTRUCTION_SIGN, REMOVE_INSTRUCTION_SIGN,
+    offset_copy_instruction::OffsetCopyInstruction, remove_instruction::RemoveInstruction,
+    InstructionBytes, InstructionContent, InstructionError, InstructionInfo, Result,
+    ADD_INSTRUCTION_SIGN, COPY_INSTRUCTION_SIGN, OFFSET_COPY_INSTRUCTION_SIGN,
+    REMOVE_INSTRUCTION_SIGN,
 };
 
 #[derive(Debug, PartialEq, Clone)]
@@ -11,6 +14,7 @@ pub enum DeltaInstruction {
     Remove(RemoveInstruction),
     Add(AddInstruction),
     Copy(CopyInstruction),
+    OffsetCopy(OffsetCopyInstruction),
 }
 
 impl InstructionInfo for DeltaInstruction {
@@ -19,6 +23,7 @@ impl InstructionInfo for DeltaInstruction {
             DeltaInstruction::Remove(instruction) => instruction.len(),
             DeltaInstruction::Add(instruction) => instruction.len(),
             DeltaInstruction::Copy(instruction) => instruction.len(),
+            DeltaInstruction::OffsetCopy(instruction) => instruction.len(),
         }
     }
 
@@ -27,6 +32,7 @@ impl InstructionInfo for DeltaInstruction {
             DeltaInstruction::Remove(instruction) => instruction.is_empty(),
             DeltaInstruction::Add(instruction) => instruction.is_empty(),
             DeltaInstruction::Copy(instruction) => instruction.is_empty(),
+            DeltaInstruction::OffsetCopy(instruction) => instruction.is_empty(),
         }
     }
 
@@ -35,6 +41,7 @@ impl InstructionInfo for DeltaInstruction {
             DeltaInstruction::Remove(instruction) => instruction.is_full(),
             DeltaInstruction::Add(instruction) => instruction.is_full(),
             DeltaInstruction::Copy(instruction) => instruction.is_full(),
+            DeltaInstruction::OffsetCopy(instruction) => instruction.is_full(),
         }
     }
 
@@ -43,6 +50,7 @@ impl InstructionInfo for DeltaInstruction {
             DeltaInstruction::Remove(instruction) => instruction.non_default_item_count(),
             DeltaInstruction::Add(instruction) => instruction.non_default_item_count(),
             DeltaInstruction::Copy(instruction) => instruction.non_default_item_count(),
+            DeltaInstruction::OffsetCopy(instruction) => instruction.non_default_item_count(),
         }
     }
 }
@@ -53,6 +61,7 @@ impl InstructionContent for DeltaInstruction {
             DeltaInstruction::Remove(instruction) => instruction.push(content),
             DeltaInstruction::Add(instruction) => instruction.push(content),
             DeltaInstruction::Copy(instruction) => instruction.push(content),
+            DeltaInstruction::OffsetCopy(instruction) => instruction.push(content),
         }
     }
 
@@ -66,14 +75,18 @@ impl InstructionContent for DeltaInstruction {
             DeltaInstruction::Remove(instruction) => instruction.fill(lcs, source, target),
             DeltaInstruction::Add(instruction) => instruction.fill(lcs, source, target),
             DeltaInstruction::Copy(instruction) => instruction.fill(lcs, source, target),
+            DeltaInstruction::OffsetCopy(instruction) => instruction.fill(lcs, source, target),
         }
     }
 
-    fn apply(&self, source: &mut Iter<'_, u8>, target: &mut Vec<u8>) {
+    fn apply(&self, source: &[u8], position: &mut usize, target: &mut Vec<u8>) {
         match self {
-            DeltaInstruction::Remove(instruction) => instruction.apply(source, target),
-            DeltaInstruction::Add(instruction) => instruction.apply(source, target),
-            DeltaInstruction::Copy(instruction) => instruction.apply(source, target),
+            DeltaInstruction::Remove(instruction) => instruction.apply(source, position, target),
+            DeltaInstruction::Add(instruction) => instruction.apply(source, position, target),
+            DeltaInstruction::Copy(instruction) => instruction.apply(source, position, target),
+            DeltaInstruction::OffsetCopy(instruction) => {
+                instruction.apply(source, position, target)
+            }
         }
     }
 }
@@ -84,6 +97,7 @@ impl InstructionBytes for DeltaInstruction {
             DeltaInstruction::Remove(instruction) => instruction.byte_sign(),
             DeltaInstruction::Add(instruction) => instruction.byte_sign(),
             DeltaInstruction::Copy(instruction) => instruction.byte_sign(),
+            DeltaInstruction::OffsetCopy(instruction) => instruction.byte_sign(),
         }
     }
 
@@ -92,6 +106,7 @@ impl InstructionBytes for DeltaInstruction {
             DeltaInstruction::Remove(instruction) => instruction.byte_length(),
             DeltaInstruction::Add(instruction) => instruction.byte_length(),
             DeltaInstruction::Copy(instruction) => instruction.byte_length(),
+            DeltaInstruction::OffsetCopy(instruction) => instruction.byte_length(),
         }
     }
 
@@ -100,10 +115,11 @@ impl InstructionBytes for DeltaInstruction {
             DeltaInstruction::Remove(instruction) => instruction.to_bytes(),
             DeltaInstruction::Add(instruction) => instruction.to_bytes(),
             DeltaInstruction::Copy(instruction) => instruction.to_bytes(),
+            DeltaInstruction::OffsetCopy(instruction) => instruction.to_bytes(),
         }
     }
 
-    fn try_from_bytes(bytes: &mut std::iter::Peekable<std::slice::Iter<'_, u8>>) -> Result<Self>
+    fn try_from_bytes(bytes: &mut core::iter::Peekable<core::slice::Iter<'_, u8>>) -> Result<Self>
     where
         Self: Sized,
     {
@@ -117,6 +133,9 @@ impl InstructionBytes for DeltaInstruction {
             Some(&&COPY_INSTRUCTION_SIGN) => Ok(DeltaInstruction::Copy(
                 CopyInstruction::try_from_bytes(bytes)?,
             )),
+            Some(&&OFFSET_COPY_INSTRUCTION_SIGN) => Ok(DeltaInstruction::OffsetCopy(
+                OffsetCopyInstruction::try_from_bytes(bytes)?,
+            )),
             None => Err(super::InstructionError::MissignSign),
             _ => Err(super::InstructionError::InvalidSign),
         }
@@ -141,6 +160,12 @@ impl From<CopyInstruction> for DeltaInstruction {
     }
 }
 
+impl From<OffsetCopyInstruction> for DeltaInstruction {
+    fn from(instruction: OffsetCopyInstruction) -> Self {
+        DeltaInstruction::OffsetCopy(instruction)
+    }
+}
+
 impl From<&DeltaInstruction> for Vec<u8> {
     fn from(value: &DeltaInstruction) -> Self {
         value.to_bytes()
@@ -156,7 +181,7 @@ impl From<DeltaInstruction> for Vec<u8> {
 impl TryFrom<&mut Peekable<Iter<'_, u8>>> for DeltaInstruction {
     type Error = InstructionError;
 
-    fn try_from(value: &mut Peekable<Iter<'_, u8>>) -> std::result::Result<Self, Self::Error> {
+    fn try_from(value: &mut Peekable<Iter<'_, u8>>) -> core::result::Result<Self, Self::Error> {
         DeltaInstruction::try_from_bytes(value)
     }
 }
@@ -164,7 +189,7 @@ impl TryFrom<&mut Peekable<Iter<'_, u8>>> for DeltaInstruction {
 impl TryFrom<Peekable<Iter<'_, u8>>> for DeltaInstruction {
     type Error = InstructionError;
 
-    fn try_from(mut value: Peekable<Iter<'_, u8>>) -> std::result::Result<Self, Self::Error> {
+    fn try_from(mut value: Peekable<Iter<'_, u8>>) -> core::result::Result<Self, Self::Error> {
         DeltaInstruction::try_from_bytes(&mut value)
     }
 }
@@ -172,7 +197,7 @@ impl TryFrom<Peekable<Iter<'_, u8>>> for DeltaInstruction {
 impl TryFrom<Vec<u8>> for DeltaInstruction {
     type Error = InstructionError;
 
-    fn try_from(value: Vec<u8>) -> std::result::Result<Self, Self::Error> {
+    fn try_from(value: Vec<u8>) -> core::result::Result<Self, Self::Error> {
         DeltaInstruction::try_from_bytes(&mut value.iter().peekable())
     }
 }
@@ -180,7 +205,7 @@ impl TryFrom<Vec<u8>> for DeltaInstruction {
 impl TryFrom<&[u8]> for DeltaInstruction {
     type Error = InstructionError;
 
-    fn try_from(value: &[u8]) -> std::result::Result<Self, Self::Error> {
+    fn try_from(value: &[u8]) -> core::result::Result<Self, Self::Error> {
         DeltaInstruction::try_from_bytes(&mut value.iter().peekable())
     }
 }
@@ -1,19 +1,31 @@
-use std::error::Error;
+use alloc::{boxed::Box, vec::Vec};
+use core::error::Error;
 
 pub mod add_instruction;
 pub mod copy_instruction;
 pub mod delta_instruction;
+pub mod offset_copy_instruction;
 pub mod remove_instruction;
 
-pub type Result<T> = std::result::Result<T, InstructionError>;
+pub type Result<T> = core::result::Result<T, InstructionError>;
 
-const REMOVE_INSTRUCTION_SIGN: u8 = b'-';
-const ADD_INSTRUCTION_SIGN: u8 = b'+';
-const COPY_INSTRUCTION_SIGN: u8 = b'|';
+pub(crate) const REMOVE_INSTRUCTION_SIGN: u8 = b'-';
+pub(crate) const ADD_INSTRUCTION_SIGN: u8 = b'+';
+pub(crate) const COPY_INSTRUCTION_SIGN: u8 = b'|';
+pub(crate) const OFFSET_COPY_INSTRUCTION_SIGN: u8 = b'@';
 
 const NON_ZERO_MAX_COUNT_PERCENT: u8 = 100;
 
-use std::{iter::Peekable, slice::Iter};
+pub(crate) fn content_length(sign: u8, length: u8) -> Result<usize> {
+    match sign {
+        REMOVE_INSTRUCTION_SIGN => Ok(0),
+        ADD_INSTRUCTION_SIGN | COPY_INSTRUCTION_SIGN => Ok(length as usize),
+        OFFSET_COPY_INSTRUCTION_SIGN => Ok(core::mem::size_of::<u64>()),
+        _ => Err(InstructionError::InvalidSign),
+    }
+}
+
+use core::{iter::Peekable, slice::Iter};
 
 pub trait InstructionInfo {
     fn len(&self) -> u8;
@@ -39,7 +51,7 @@ pub trait InstructionContent {
         target: &mut Peekable<Iter<'_, u8>>,
     );
 
-    fn apply(&self, source: &mut Iter<'_, u8>, target: &mut Vec<u8>);
+    fn apply(&self, source: &[u8], position: &mut usize, target: &mut Vec<u8>);
 }
 pub trait InstructionBytes {
     fn byte_sign(&self) -> u8;
@@ -62,10 +74,44 @@ pub enum InstructionError {
     InvalidLength,
     MissingContent,
     InvalidContent,
+    InvalidHeader,
+    UnsupportedCompression,
+    InvalidCompression,
+    InvalidChecksum,
+    UnsupportedFeature,
+    SourceMismatch,
+    InvalidAssembly {
+        line: usize,
+    },
+    InvalidInstruction {
+        index: usize,
+        offset: usize,
+        sign: u8,
+        error: Box<InstructionError>,
+    },
+    OutputTooSmall {
+        available: usize,
+        required: usize,
+    },
+    IndexOutOfRange {
+        index: usize,
+        length: usize,
+    },
+}
+
+impl InstructionError {
+    pub(crate) fn at(self, index: usize, offset: usize, sign: u8) -> Self {
+        InstructionError::InvalidInstruction {
+            index,
+            offset,
+            sign,
+            error: Box::new(self),
+        }
+    }
 }
 
-impl std::fmt::Display for InstructionError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl core::fmt::Display for InstructionError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             InstructionError::ContentOverflow => {
                 write!(
@@ -77,8 +123,11 @@ impl std::fmt::Display for InstructionError {
             InstructionError::MissignSign => write!(f, "No instruction sign found"),
             InstructionError::InvalidSign => write!(
                 f,
-                "Instruction sign didn't match: {}, {} or {}",
-                REMOVE_INSTRUCTION_SIGN, ADD_INSTRUCTION_SIGN, COPY_INSTRUCTION_SIGN
+                "Instruction sign didn't match: '{}', '{}', '{}' or '{}'",
+                REMOVE_INSTRUCTION_SIGN as char,
+                ADD_INSTRUCTION_SIGN as char,
+                COPY_INSTRUCTION_SIGN as char,
+                OFFSET_COPY_INSTRUCTION_SIGN as char
             ),
             InstructionError::MissingLength => write!(f, "No length value found"),
             InstructionError::MissingContent => {
@@ -87,27 +136,77 @@ impl std::fmt::Display for InstructionError {
             InstructionError::InvalidLength => write!(
                 f,
                 "Not enough bytes found to create a length of type {}",
-                std::any::type_name::<u8>()
+                core::any::type_name::<u8>()
+            ),
+            InstructionError::InvalidContent => {
+                write!(f, "Content is malformed or refers to data out of range")
+            }
+            InstructionError::InvalidHeader => write!(f, "Header is malformed"),
+            InstructionError::UnsupportedCompression => {
+                write!(f, "Compression is unknown or disabled")
+            }
+            InstructionError::InvalidCompression => write!(
+                f,
+                "Compressed stream is corrupt or expands beyond the size limit"
             ),
-            InstructionError::InvalidContent => write!(
+            InstructionError::InvalidChecksum => {
+                write!(f, "Checksum of the reconstructed data didn't match")
+            }
+            InstructionError::UnsupportedFeature => {
+                write!(f, "Patch uses a format feature that isn't supported")
+            }
+            InstructionError::SourceMismatch => {
+                write!(f, "Source doesn't match the one the patch was made for")
+            }
+            InstructionError::InvalidAssembly { line } => {
+                write!(f, "Patch assembly is malformed at line {}", line)
+            }
+            InstructionError::InvalidInstruction {
+                index,
+                offset,
+                sign,
+                error,
+            } => write!(
                 f,
-                "Not enough bytes found to create an item item of type {}",
-                std::any::type_name::<u8>()
+                "Instruction {} with sign {:#04x} at byte offset {}: {}",
+                index, sign, offset, error
             ),
+            InstructionError::OutputTooSmall {
+                available,
+                required,
+            } => write!(
+                f,
+                "Output buffer of {} bytes can't hold {} bytes",
+                available, required
+            ),
+            InstructionError::IndexOutOfRange { index, length } => {
+                write!(f, "Index {} is out of range for {} entries", index, length)
+            }
         }
     }
 }
 
-impl Error for InstructionError {}
+impl Error for InstructionError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            InstructionError::InvalidInstruction { error, .. } => Some(error.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<InstructionError> for std::io::Error {
+    fn from(error: InstructionError) -> Self {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, error)
+    }
+}
 
 #[cfg(test)]
 mod instruction_mod_tests {
     use super::*;
 
-    fn threshold(
-        len: u8,
-        non_zero_max_count_percent: u8,
-    ) -> u8 {
+    fn threshold(len: u8, non_zero_max_count_percent: u8) -> u8 {
         ((len as f32 * non_zero_max_count_percent as f32) / 100f32) as u8
     }
 
@@ -117,4 +216,33 @@ mod instruction_mod_tests {
             assert_eq!(threshold(len, NON_ZERO_MAX_COUNT_PERCENT), len);
         }
     }
+
+    #[test]
+    fn instruction_error_display() {
+        assert_eq!(
+            InstructionError::InvalidSign.to_string(),
+            "Instruction sign didn't match: '-', '+', '|' or '@'"
+        );
+        let error = InstructionError::MissingContent.at(3, 1024, ADD_INSTRUCTION_SIGN);
+        assert_eq!(
+            error.to_string(),
+            "Instruction 3 with sign 0x2b at byte offset 1024: \
+             Not enough bytes found to match the given length"
+        );
+        assert_eq!(
+            error.source().unwrap().to_string(),
+            InstructionError::MissingContent.to_string()
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn io_error() {
+        let error = std::io::Error::from(InstructionError::SourceMismatch);
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(
+            error.to_string(),
+            InstructionError::SourceMismatch.to_string()
+        );
+    }
 }
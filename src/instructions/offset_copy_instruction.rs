@@ -0,0 +1,234 @@
+use alloc::vec::Vec;
+use core::{iter::Peekable, slice::Iter};
+
+use super::{
+    InstructionBytes, InstructionContent, InstructionError, InstructionInfo, Result,
+    OFFSET_COPY_INSTRUCTION_SIGN,
+};
+
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct OffsetCopyInstruction {
+    offset: u64,
+    length: u8,
+}
+
+impl OffsetCopyInstruction {
+    pub fn new(offset: u64, length: u8) -> Self {
+        Self { offset, length }
+    }
+
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    pub fn end(&self) -> Option<u64> {
+        self.offset.checked_add(self.length as u64)
+    }
+}
+
+impl InstructionInfo for OffsetCopyInstruction {
+    fn len(&self) -> u8 {
+        self.length
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len() == u8::MIN
+    }
+
+    fn is_full(&self) -> bool {
+        self.len() == u8::MAX
+    }
+
+    fn non_default_item_count(&self) -> Option<u8> {
+        None
+    }
+}
+
+impl InstructionContent for OffsetCopyInstruction {
+    fn push(&mut self, _: u8) -> Result<()> {
+        if self.is_full() {
+            return Err(InstructionError::ContentOverflow);
+        }
+        self.length += 1;
+        Ok(())
+    }
+
+    fn fill(
+        &mut self,
+        _: &mut Peekable<Iter<'_, u8>>,
+        _: &mut Peekable<Iter<'_, u8>>,
+        _: &mut Peekable<Iter<'_, u8>>,
+    ) {
+    }
+
+    fn apply(&self, source: &[u8], _: &mut usize, target: &mut Vec<u8>) {
+        let offset = self.offset as usize;
+        target.extend_from_slice(&source[offset..offset + self.length as usize]);
+    }
+}
+
+impl InstructionBytes for OffsetCopyInstruction {
+    fn byte_sign(&self) -> u8 {
+        OFFSET_COPY_INSTRUCTION_SIGN
+    }
+
+    fn byte_length(&self) -> usize {
+        2 + core::mem::size_of::<u64>()
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = Vec::with_capacity(self.byte_length());
+        bytes.push(self.byte_sign());
+        bytes.push(self.len());
+        bytes.extend(self.offset.to_be_bytes());
+        bytes
+    }
+
+    fn try_from_bytes(bytes: &mut Peekable<Iter<'_, u8>>) -> Result<Self> {
+        match bytes.next() {
+            Some(&OFFSET_COPY_INSTRUCTION_SIGN) => (),
+            Some(_) => return Err(InstructionError::InvalidSign),
+            None => return Err(InstructionError::MissignSign),
+        };
+
+        let length = *bytes.next().ok_or(InstructionError::MissingLength)?;
+        let offset: Vec<u8> = bytes.take(core::mem::size_of::<u64>()).copied().collect();
+        let offset: [u8; 8] = offset
+            .try_into()
+            .map_err(|_| InstructionError::MissingContent)?;
+
+        let offset = u64::from_be_bytes(offset);
+        offset
+            .checked_add(length as u64)
+            .ok_or(InstructionError::InvalidContent)?;
+        Ok(Self { offset, length })
+    }
+}
+
+impl From<&OffsetCopyInstruction> for Vec<u8> {
+    fn from(value: &OffsetCopyInstruction) -> Self {
+        value.to_bytes()
+    }
+}
+
+impl From<OffsetCopyInstruction> for Vec<u8> {
+    fn from(value: OffsetCopyInstruction) -> Self {
+        value.to_bytes()
+    }
+}
+
+impl TryFrom<&mut Peekable<Iter<'_, u8>>> for OffsetCopyInstruction {
+    type Error = InstructionError;
+
+    fn try_from(value: &mut Peekable<Iter<'_, u8>>) -> core::result::Result<Self, Self::Error> {
+        OffsetCopyInstruction::try_from_bytes(value)
+    }
+}
+
+impl TryFrom<Peekable<Iter<'_, u8>>> for OffsetCopyInstruction {
+    type Error = InstructionError;
+
+    fn try_from(mut value: Peekable<Iter<'_, u8>>) -> core::result::Result<Self, Self::Error> {
+        OffsetCopyInstruction::try_from_bytes(&mut value)
+    }
+}
+
+impl TryFrom<Vec<u8>> for OffsetCopyInstruction {
+    type Error = InstructionError;
+
+    fn try_from(value: Vec<u8>) -> core::result::Result<Self, Self::Error> {
+        OffsetCopyInstruction::try_from_bytes(&mut value.iter().peekable())
+    }
+}
+
+impl TryFrom<&[u8]> for OffsetCopyInstruction {
+    type Error = InstructionError;
+
+    fn try_from(value: &[u8]) -> core::result::Result<Self, Self::Error> {
+        OffsetCopyInstruction::try_from_bytes(&mut value.iter().peekable())
+    }
+}
+
+#[cfg(test)]
+mod offset_copy_instruction_tests {
+    use super::*;
+
+    #[test]
+    fn instruction_info() {
+        let mut instruction = OffsetCopyInstruction::new(7, u8::MAX);
+        assert_eq!(instruction.len(), u8::MAX);
+        assert_eq!(instruction.end(), Some(7 + u8::MAX as u64));
+        assert_eq!(OffsetCopyInstruction::new(u64::MAX, 1).end(), None);
+        assert!(instruction.is_full());
+
+        instruction = OffsetCopyInstruction::new(7, u8::MIN);
+        assert_eq!(instruction.len(), u8::MIN);
+        assert!(instruction.is_empty());
+    }
+
+    #[test]
+    fn instruction_content_push() {
+        let mut instruction = OffsetCopyInstruction::new(0, u8::MAX - 1);
+        assert!(instruction.push(0).is_ok());
+        assert_eq!(instruction.push(0), Err(InstructionError::ContentOverflow));
+    }
+
+    #[test]
+    fn instruction_content_apply() {
+        let instruction = OffsetCopyInstruction::new(1, 2);
+        let mut position = 3;
+        let mut target = b"X".to_vec();
+        instruction.apply(b"ABCD", &mut position, &mut target);
+        assert_eq!(target, b"XBC");
+        assert_eq!(position, 3);
+    }
+
+    #[test]
+    fn instruction_bytes_to_bytes() {
+        let instruction = OffsetCopyInstruction::new(258, u8::MAX);
+        let mut bytes = vec![OFFSET_COPY_INSTRUCTION_SIGN, u8::MAX];
+        bytes.extend(258u64.to_be_bytes());
+        assert_eq!(instruction.to_bytes(), bytes);
+        assert_eq!(instruction.byte_length(), bytes.len());
+    }
+
+    #[test]
+    fn instruction_bytes_try_from_bytes_ok() {
+        let instruction = OffsetCopyInstruction::new(u64::MAX - u8::MAX as u64, 3);
+        let bytes = instruction.to_bytes();
+        assert_eq!(
+            OffsetCopyInstruction::try_from_bytes(&mut bytes.iter().peekable()),
+            Ok(instruction)
+        );
+    }
+
+    #[test]
+    fn instruction_bytes_try_from_bytes_err() {
+        let mut bytes: Vec<u8> = vec![];
+        assert_eq!(
+            OffsetCopyInstruction::try_from_bytes(&mut bytes.iter().peekable()),
+            Err(InstructionError::MissignSign)
+        );
+        bytes = vec![b'A'];
+        assert_eq!(
+            OffsetCopyInstruction::try_from_bytes(&mut bytes.iter().peekable()),
+            Err(InstructionError::InvalidSign)
+        );
+        bytes = vec![OFFSET_COPY_INSTRUCTION_SIGN];
+        assert_eq!(
+            OffsetCopyInstruction::try_from_bytes(&mut bytes.iter().peekable()),
+            Err(InstructionError::MissingLength)
+        );
+        bytes = vec![OFFSET_COPY_INSTRUCTION_SIGN, 1, 0, 0];
+        assert_eq!(
+            OffsetCopyInstruction::try_from_bytes(&mut bytes.iter().peekable()),
+            Err(InstructionError::MissingContent)
+        );
+        bytes = vec![OFFSET_COPY_INSTRUCTION_SIGN, 3];
+        bytes.extend(u64::MAX.to_be_bytes());
+        assert_eq!(
+            OffsetCopyInstruction::try_from_bytes(&mut bytes.iter().peekable()),
+            Err(InstructionError::InvalidContent)
+        );
+    }
+}
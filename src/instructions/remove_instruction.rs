@@ -1,4 +1,5 @@
-use std::{iter::Peekable, slice::Iter};
+use alloc::{vec, vec::Vec};
+use core::{iter::Peekable, slice::Iter};
 
 use super::{
     InstructionBytes, InstructionContent, InstructionError, InstructionInfo, Result,
@@ -54,10 +55,8 @@ impl InstructionContent for RemoveInstruction {
         }
     }
 
-    fn apply(&self, source: &mut Iter<'_, u8>, _: &mut Vec<u8>) {
-        for _ in 0..self.len() {
-            source.next();
-        }
+    fn apply(&self, _: &[u8], position: &mut usize, _: &mut Vec<u8>) {
+        *position += self.len() as usize;
     }
 }
 
@@ -111,7 +110,7 @@ impl From<RemoveInstruction> for Vec<u8> {
 impl TryFrom<&mut Peekable<Iter<'_, u8>>> for RemoveInstruction {
     type Error = InstructionError;
 
-    fn try_from(value: &mut Peekable<Iter<'_, u8>>) -> std::result::Result<Self, Self::Error> {
+    fn try_from(value: &mut Peekable<Iter<'_, u8>>) -> core::result::Result<Self, Self::Error> {
         RemoveInstruction::try_from_bytes(value)
     }
 }
@@ -119,7 +118,7 @@ impl TryFrom<&mut Peekable<Iter<'_, u8>>> for RemoveInstruction {
 impl TryFrom<Peekable<Iter<'_, u8>>> for RemoveInstruction {
     type Error = InstructionError;
 
-    fn try_from(mut value: Peekable<Iter<'_, u8>>) -> std::result::Result<Self, Self::Error> {
+    fn try_from(mut value: Peekable<Iter<'_, u8>>) -> core::result::Result<Self, Self::Error> {
         RemoveInstruction::try_from_bytes(&mut value)
     }
 }
@@ -127,7 +126,7 @@ impl TryFrom<Peekable<Iter<'_, u8>>> for RemoveInstruction {
 impl TryFrom<Vec<u8>> for RemoveInstruction {
     type Error = InstructionError;
 
-    fn try_from(value: Vec<u8>) -> std::result::Result<Self, Self::Error> {
+    fn try_from(value: Vec<u8>) -> core::result::Result<Self, Self::Error> {
         RemoveInstruction::try_from_bytes(&mut value.iter().peekable())
     }
 }
@@ -135,7 +134,7 @@ impl TryFrom<Vec<u8>> for RemoveInstruction {
 impl TryFrom<&[u8]> for RemoveInstruction {
     type Error = InstructionError;
 
-    fn try_from(value: &[u8]) -> std::result::Result<Self, Self::Error> {
+    fn try_from(value: &[u8]) -> core::result::Result<Self, Self::Error> {
         RemoveInstruction::try_from_bytes(&mut value.iter().peekable())
     }
 }
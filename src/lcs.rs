@@ -1,3 +1,5 @@
+use alloc::{vec, vec::Vec};
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Lcs<'a> {
     source: &'a [u8],
@@ -1,6 +1,49 @@
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+mod assembly;
+#[cfg(feature = "tokio")]
+pub mod async_io;
+#[cfg(feature = "bsdiff")]
+mod bsdiff;
+mod builder;
+mod chunking;
+mod container;
+mod crc32;
+#[cfg(feature = "std")]
+pub mod file;
+mod git_delta;
+mod in_place;
 mod instructions;
 mod lcs;
+#[cfg(feature = "memmap")]
+mod mmap;
+mod multi;
+mod operation;
+pub mod pack;
+mod parallel;
 pub mod patch;
+pub mod rom;
+pub mod seek;
+#[cfg(feature = "serde")]
+mod serialization;
+pub mod similarity;
+mod sink;
+pub mod store;
+#[cfg(feature = "std")]
+pub mod tree;
+mod vcdiff;
+pub mod view;
+
+pub use container::Compression;
+pub use instructions::InstructionError;
+pub use instructions::{
+    add_instruction::AddInstruction, copy_instruction::CopyInstruction,
+    delta_instruction::DeltaInstruction, offset_copy_instruction::OffsetCopyInstruction,
+    remove_instruction::RemoveInstruction,
+};
+pub use sink::Sink;
 
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,326 @@
+use std::{
+    env, fmt, fs,
+    io::{self, Read, Write},
+    process::ExitCode,
+};
+
+use deltas::{
+    patch::Patch,
+    view::{InstructionView, PatchView},
+    InstructionError,
+};
+
+const STDIO_PATH: &str = "-";
+const EXIT_IO: u8 = 1;
+const EXIT_USAGE: u8 = 2;
+const EXIT_INVALID_PATCH: u8 = 3;
+const EXIT_SOURCE_MISMATCH: u8 = 4;
+
+const USAGE: &str = "\
+Usage:
+    deltas diff OLD NEW [-o PATCH]
+    deltas apply OLD PATCH [-o NEW]
+    deltas inspect PATCH
+
+Use - as a path to read from stdin or write to stdout (the default output).";
+
+#[derive(Debug, PartialEq)]
+enum Command {
+    Diff {
+        old: String,
+        new: String,
+        output: String,
+    },
+    Apply {
+        old: String,
+        patch: String,
+        output: String,
+    },
+    Inspect {
+        patch: String,
+    },
+    Help,
+}
+
+#[derive(Debug)]
+enum CliError {
+    Usage(String),
+    Io(String, io::Error),
+    Patch(InstructionError),
+}
+
+impl CliError {
+    fn exit_code(&self) -> u8 {
+        match self {
+            CliError::Usage(_) => EXIT_USAGE,
+            CliError::Io(..) => EXIT_IO,
+            CliError::Patch(InstructionError::SourceMismatch) => EXIT_SOURCE_MISMATCH,
+            CliError::Patch(_) => EXIT_INVALID_PATCH,
+        }
+    }
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Usage(message) => write!(f, "{}\n\n{}", message, USAGE),
+            CliError::Io(path, error) => write!(f, "{}: {}", path, error),
+            CliError::Patch(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl From<InstructionError> for CliError {
+    fn from(value: InstructionError) -> Self {
+        CliError::Patch(value)
+    }
+}
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Command, CliError> {
+    let mut positional: Vec<String> = Vec::new();
+    let mut output: Option<String> = None;
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "-o" | "--output" => {
+                let path = args
+                    .next()
+                    .ok_or_else(|| CliError::Usage(format!("{} requires a path", arg)))?;
+                output = Some(path);
+            }
+            _ if arg.starts_with('-') && arg != STDIO_PATH => {
+                return Err(CliError::Usage(format!("unknown option {}", arg)))
+            }
+            _ => positional.push(arg),
+        }
+    }
+
+    let mut positional = positional.into_iter();
+    let command = positional
+        .next()
+        .ok_or_else(|| CliError::Usage(String::from("missing command")))?;
+    let operands: Vec<String> = positional.collect();
+    let output = output.unwrap_or_else(|| String::from(STDIO_PATH));
+    let command = match (command.as_str(), <[String; 2]>::try_from(operands.clone())) {
+        ("diff", Ok([old, new])) => Command::Diff { old, new, output },
+        ("apply", Ok([old, patch])) => Command::Apply { old, patch, output },
+        ("inspect", _) if operands.len() == 1 => Command::Inspect {
+            patch: operands[0].clone(),
+        },
+        ("diff" | "apply" | "inspect", _) => {
+            return Err(CliError::Usage(format!(
+                "wrong number of arguments for {}",
+                command
+            )))
+        }
+        _ => return Err(CliError::Usage(format!("unknown command {}", command))),
+    };
+    match &command {
+        Command::Diff { old, new, .. }
+        | Command::Apply {
+            old, patch: new, ..
+        } if old == STDIO_PATH && new == STDIO_PATH => Err(CliError::Usage(String::from(
+            "only one input can be read from stdin",
+        ))),
+        _ => Ok(command),
+    }
+}
+
+fn read_input(path: &str) -> Result<Vec<u8>, CliError> {
+    let mut bytes: Vec<u8> = Vec::new();
+    let result = if path == STDIO_PATH {
+        io::stdin().lock().read_to_end(&mut bytes).map(|_| ())
+    } else {
+        fs::read(path).map(|content| bytes = content)
+    };
+    result.map_err(|error| CliError::Io(String::from(path), error))?;
+    Ok(bytes)
+}
+
+fn write_output(path: &str, bytes: &[u8]) -> Result<(), CliError> {
+    let result = if path == STDIO_PATH {
+        let mut stdout = io::stdout().lock();
+        stdout.write_all(bytes).and_then(|_| stdout.flush())
+    } else {
+        fs::write(path, bytes)
+    };
+    result.map_err(|error| CliError::Io(String::from(path), error))
+}
+
+fn inspect(bytes: &[u8]) -> Result<String, CliError> {
+    let raw = Patch::try_from_bytes(bytes)?.to_bytes();
+    let view = PatchView::new(&raw)?;
+    let mut counts = [(0usize, 0usize); 4];
+    for instruction in view.instructions() {
+        let index = match instruction {
+            InstructionView::Remove(_) => 0,
+            InstructionView::Add(_) => 1,
+            InstructionView::Copy(_) => 2,
+            InstructionView::OffsetCopy { .. } => 3,
+        };
+        counts[index].0 += 1;
+        counts[index].1 += instruction.len() as usize;
+    }
+
+    let mut report = format!(
+        "patch size:    {} bytes{}\nsource length: {} bytes\ntarget length: {} bytes\n",
+        bytes.len(),
+        if raw.len() == bytes.len() {
+            String::new()
+        } else {
+            format!(" ({} bytes uncompressed)", raw.len())
+        },
+        view.source_length(),
+        view.target_length()
+    );
+    for (name, (count, length)) in ["remove", "add", "copy", "offset_copy"].iter().zip(counts) {
+        report.push_str(&format!(
+            "{:<14} {} instructions, {} bytes\n",
+            format!("{}:", name),
+            count,
+            length
+        ));
+    }
+    Ok(report)
+}
+
+fn run(command: Command) -> Result<(), CliError> {
+    match command {
+        Command::Diff { old, new, output } => {
+            let patch = Patch::new(&read_input(&old)?, &read_input(&new)?);
+            write_output(&output, &patch.to_bytes())
+        }
+        Command::Apply { old, patch, output } => {
+            let source = read_input(&old)?;
+            let patch = Patch::try_from_bytes(&read_input(&patch)?)?;
+            let target = patch
+                .apply(&source)
+                .ok_or(InstructionError::SourceMismatch)?;
+            write_output(&output, &target)
+        }
+        Command::Inspect { patch } => {
+            write_output(STDIO_PATH, inspect(&read_input(&patch)?)?.as_bytes())
+        }
+        Command::Help => write_output(STDIO_PATH, format!("{}\n", USAGE).as_bytes()),
+    }
+}
+
+fn main() -> ExitCode {
+    match parse_args(env::args().skip(1)).and_then(run) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("deltas: {}", error);
+            ExitCode::from(error.exit_code())
+        }
+    }
+}
+
+#[cfg(test)]
+mod main_tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            parse_args(args("diff old new -o patch")).unwrap(),
+            Command::Diff {
+                old: String::from("old"),
+                new: String::from("new"),
+                output: String::from("patch")
+            }
+        );
+        assert_eq!(
+            parse_args(args("apply - patch")).unwrap(),
+            Command::Apply {
+                old: String::from("-"),
+                patch: String::from("patch"),
+                output: String::from("-")
+            }
+        );
+        assert_eq!(
+            parse_args(args("inspect patch")).unwrap(),
+            Command::Inspect {
+                patch: String::from("patch")
+            }
+        );
+        assert_eq!(parse_args(args("diff --help")).unwrap(), Command::Help);
+    }
+
+    #[test]
+    fn parse_err() {
+        for line in [
+            "",
+            "merge a b",
+            "diff old",
+            "inspect a b",
+            "diff old new -o",
+            "diff old new --force",
+            "apply - -",
+        ] {
+            let error = parse_args(args(line)).unwrap_err();
+            assert_eq!(error.exit_code(), EXIT_USAGE, "{}", line);
+        }
+    }
+
+    #[test]
+    fn exit_codes() {
+        assert_eq!(
+            CliError::from(InstructionError::SourceMismatch).exit_code(),
+            EXIT_SOURCE_MISMATCH
+        );
+        assert_eq!(
+            CliError::from(InstructionError::InvalidSign).exit_code(),
+            EXIT_INVALID_PATCH
+        );
+        let error = read_input("files/missing").unwrap_err();
+        assert_eq!(error.exit_code(), EXIT_IO);
+
+        let mut overflow = b"@\x03".to_vec();
+        overflow.extend([u8::MAX; 8]);
+        overflow.extend(b"|\x03\x00\x00\x00");
+        assert_eq!(
+            inspect(&overflow).unwrap_err().exit_code(),
+            EXIT_INVALID_PATCH
+        );
+    }
+
+    #[test]
+    fn diff_apply_inspect() {
+        let directory = env::temp_dir().join(format!("deltas_cli_{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let patch = directory.join("patch").to_string_lossy().into_owned();
+        let target = directory.join("target").to_string_lossy().into_owned();
+
+        run(parse_args(args(&format!(
+            "diff files/source.txt files/target.txt -o {}",
+            patch
+        )))
+        .unwrap())
+        .unwrap();
+        run(parse_args(args(&format!(
+            "apply files/source.txt {} -o {}",
+            patch, target
+        )))
+        .unwrap())
+        .unwrap();
+        assert_eq!(
+            fs::read(&target).unwrap(),
+            fs::read("files/target.txt").unwrap()
+        );
+
+        let error = run(parse_args(args(&format!("apply files/target.txt {}", patch))).unwrap())
+            .unwrap_err();
+        assert_eq!(error.exit_code(), EXIT_SOURCE_MISMATCH);
+
+        let report = inspect(&fs::read(&patch).unwrap()).unwrap();
+        let source_length = fs::read("files/source.txt").unwrap().len();
+        assert!(report.contains(&format!("source length: {} bytes", source_length)));
+        assert!(report.contains("offset_copy:   0 instructions, 0 bytes"));
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,112 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io,
+    path::Path,
+};
+
+use memmap2::{Mmap, MmapMut};
+
+use crate::{file::temporary_path, instructions::InstructionError, patch::Patch};
+
+// Mapped files must not be truncated by another process while they are in use, otherwise
+// reading the mapping faults; this is the usual contract for memory-mapped IO.
+fn map(path: &Path) -> io::Result<Mmap> {
+    let file = File::open(path)?;
+    unsafe { Mmap::map(&file) }
+}
+
+impl Patch {
+    pub fn from_files(source: impl AsRef<Path>, target: impl AsRef<Path>) -> io::Result<Self> {
+        let source = map(source.as_ref())?;
+        let target = map(target.as_ref())?;
+        Ok(Patch::new(&source, &target))
+    }
+
+    pub fn apply_files(
+        &self,
+        source: impl AsRef<Path>,
+        target: impl AsRef<Path>,
+    ) -> io::Result<()> {
+        let source = map(source.as_ref())?;
+        if source.len() != self.source_lenth() || !self.offsets_fit(source.len()) {
+            return Err(InstructionError::SourceMismatch.into());
+        }
+        // Writing through a sibling file keeps the source mapping intact when both paths
+        // name the same file.
+        let target = target.as_ref();
+        let temporary = temporary_path(target);
+        let result = self
+            .write_mapped(&source, &temporary)
+            .and_then(|()| fs::rename(&temporary, target));
+        if result.is_err() {
+            let _ = fs::remove_file(&temporary);
+        }
+        result
+    }
+
+    fn write_mapped(&self, source: &[u8], path: &Path) -> io::Result<()> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(path)?;
+        let target_length = self.target_length();
+        file.set_len(target_length as u64)?;
+        if target_length == 0 {
+            return Ok(());
+        }
+        // SAFETY: the file was created above with a unique name and is only written through
+        // this mapping until it is renamed into place.
+        let mut target = unsafe { MmapMut::map_mut(&file)? };
+        self.apply_into(source, &mut target)?;
+        target.flush()
+    }
+}
+
+#[cfg(test)]
+mod mmap_tests {
+    use std::{env, fs};
+
+    use super::*;
+
+    #[test]
+    fn from_files() {
+        let source = fs::read("files/source.txt").unwrap();
+        let target = fs::read("files/target.txt").unwrap();
+        let patch = Patch::from_files("files/source.txt", "files/target.txt").unwrap();
+        assert_eq!(patch, Patch::new(&source, &target));
+    }
+
+    #[test]
+    fn apply_files() {
+        let path = env::temp_dir().join(format!("deltas_mmap_{}", std::process::id()));
+        let patch = Patch::from_files("files/source.txt", "files/target.txt").unwrap();
+        patch.apply_files("files/source.txt", &path).unwrap();
+        assert_eq!(
+            fs::read(&path).unwrap(),
+            fs::read("files/target.txt").unwrap()
+        );
+
+        let error = patch.apply_files("files/target.txt", &path).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        let empty = env::temp_dir().join(format!("deltas_mmap_empty_{}", std::process::id()));
+        fs::write(&empty, b"").unwrap();
+        let patch = Patch::from_files(&empty, "files/source.txt").unwrap();
+        patch.apply_files(&empty, &path).unwrap();
+        assert_eq!(
+            fs::read(&path).unwrap(),
+            fs::read("files/source.txt").unwrap()
+        );
+
+        let patch = Patch::from_files("files/source.txt", "files/target.txt").unwrap();
+        fs::copy("files/source.txt", &path).unwrap();
+        patch.apply_files(&path, &path).unwrap();
+        assert_eq!(
+            fs::read(&path).unwrap(),
+            fs::read("files/target.txt").unwrap()
+        );
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(&empty).unwrap();
+    }
+}
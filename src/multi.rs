@@ -0,0 +1,227 @@
+use alloc::{collections::BTreeMap, vec::Vec};
+
+use crate::{
+    builder::PatchBuilder,
+    instructions::{delta_instruction::DeltaInstruction, InstructionInfo},
+    patch::Patch,
+};
+
+const SEED_LENGTH: usize = 8;
+const MIN_MATCH_LENGTH: usize = 12;
+const MAX_CANDIDATES: usize = 16;
+
+pub(crate) struct Dictionary<'a> {
+    sources: Vec<&'a [u8]>,
+    starts: Vec<usize>,
+    length: usize,
+    seeds: BTreeMap<&'a [u8], Vec<usize>>,
+}
+
+impl<'a> Dictionary<'a> {
+    pub(crate) fn new<S: AsRef<[u8]>>(sources: &'a [S]) -> Self {
+        let mut starts: Vec<usize> = Vec::with_capacity(sources.len());
+        let mut length = 0;
+        let mut seeds: BTreeMap<&'a [u8], Vec<usize>> = BTreeMap::new();
+        for source in sources.iter().map(AsRef::as_ref) {
+            for (index, seed) in source.windows(SEED_LENGTH).enumerate() {
+                let candidates = seeds.entry(seed).or_default();
+                if candidates.len() < MAX_CANDIDATES {
+                    candidates.push(length + index);
+                }
+            }
+            starts.push(length);
+            length += source.len();
+        }
+        Self {
+            sources: sources.iter().map(AsRef::as_ref).collect(),
+            starts,
+            length,
+            seeds,
+        }
+    }
+
+    fn longest_match(&self, target: &[u8]) -> Option<(usize, usize)> {
+        let seed = target.get(..SEED_LENGTH)?;
+        self.seeds
+            .get(seed)?
+            .iter()
+            .map(|offset| {
+                let index = self.starts.partition_point(|start| start <= offset) - 1;
+                let length = self.sources[index][offset - self.starts[index]..]
+                    .iter()
+                    .zip(target)
+                    .take_while(|(source_item, item)| source_item == item)
+                    .count();
+                (*offset, length)
+            })
+            .filter(|(_, length)| *length >= MIN_MATCH_LENGTH)
+            .fold(None, |best: Option<(usize, usize)>, candidate| match best {
+                Some(best) if best.1 >= candidate.1 => Some(best),
+                _ => Some(candidate),
+            })
+    }
+
+    pub(crate) fn diff_into(&self, builder: &mut PatchBuilder, target: &[u8]) {
+        let mut literal_start = 0;
+        let mut position = 0;
+        while position < target.len() {
+            match self.longest_match(&target[position..]) {
+                Some((offset, length)) => {
+                    builder.add(&target[literal_start..position]);
+                    builder.copy_offset(offset, length);
+                    position += length;
+                    literal_start = position;
+                }
+                None => position += 1,
+            }
+        }
+        builder.add(&target[literal_start..]);
+    }
+}
+
+fn read_sources<S: AsRef<[u8]>>(
+    sources: &[S],
+    mut offset: usize,
+    mut length: usize,
+    target: &mut Vec<u8>,
+) -> Option<()> {
+    for source in sources.iter().map(AsRef::as_ref) {
+        if length == 0 {
+            break;
+        }
+        if offset >= source.len() {
+            offset -= source.len();
+            continue;
+        }
+        let end = source.len().min(offset + length);
+        target.extend_from_slice(&source[offset..end]);
+        length -= end - offset;
+        offset = 0;
+    }
+    (length == 0).then_some(())
+}
+
+impl Patch {
+    /// Offsets address the sources as if they were concatenated in order. Matches stop at
+    /// source boundaries, although adjacent copies may still merge into one instruction.
+    pub fn new_multi<S: AsRef<[u8]>>(sources: &[S], target: &[u8]) -> Self {
+        let dictionary = Dictionary::new(sources);
+        let mut builder = PatchBuilder::new();
+        dictionary.diff_into(&mut builder, target);
+        builder.finish(dictionary.length)
+    }
+
+    pub fn apply_multi<S: AsRef<[u8]>>(&self, sources: &[S]) -> Option<Vec<u8>> {
+        let source_length = sources.iter().map(|source| source.as_ref().len()).sum();
+        if source_length != self.source_lenth() || !self.offsets_fit(source_length) {
+            return None;
+        }
+        let mut target: Vec<u8> = Vec::with_capacity(self.target_length());
+        let mut position = 0;
+        for instruction in self.instructions() {
+            let length = instruction.len() as usize;
+            match instruction {
+                DeltaInstruction::Remove(_) => position += length,
+                DeltaInstruction::Add(instruction) => {
+                    target.extend_from_slice(instruction.content())
+                }
+                DeltaInstruction::Copy(instruction) => {
+                    let start = target.len();
+                    read_sources(sources, position, length, &mut target)?;
+                    for (item, difference) in target[start..].iter_mut().zip(instruction.content())
+                    {
+                        *item = item.wrapping_add(*difference);
+                    }
+                    position += length;
+                }
+                DeltaInstruction::OffsetCopy(instruction) => {
+                    read_sources(sources, instruction.offset() as usize, length, &mut target)?
+                }
+            }
+        }
+        Some(target)
+    }
+}
+
+#[cfg(test)]
+mod multi_tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn new_multi() {
+        let library_a = fs::read("files/source.txt").unwrap();
+        let library_b = fs::read("files/target.txt").unwrap();
+        let mut bundle = b"// bundle header\n".to_vec();
+        bundle.extend_from_slice(&library_b[100..900]);
+        bundle.extend_from_slice(b"\n// glue\n");
+        bundle.extend_from_slice(&library_a[..1500]);
+        bundle.extend_from_slice(&library_b[2000..]);
+
+        let sources = [library_a.as_slice(), library_b.as_slice()];
+        let patch = Patch::new_multi(&sources, &bundle);
+        assert_eq!(patch.apply_multi(&sources), Some(bundle.clone()));
+        assert!(patch.to_bytes().len() < bundle.len() / 4);
+
+        let offsets: Vec<u64> = patch
+            .instructions()
+            .iter()
+            .filter_map(|instruction| match instruction {
+                DeltaInstruction::OffsetCopy(instruction) => Some(instruction.offset()),
+                _ => None,
+            })
+            .collect();
+        assert!(offsets
+            .iter()
+            .any(|offset| *offset < library_a.len() as u64));
+        assert!(offsets
+            .iter()
+            .any(|offset| *offset >= library_a.len() as u64));
+
+        assert_eq!(patch.apply_multi(&sources[..1]), None);
+        let decoded = Patch::try_from_bytes(&patch.to_bytes()).unwrap();
+        assert_eq!(decoded.apply_multi(&sources), Some(bundle));
+    }
+
+    #[test]
+    fn sources_order() {
+        let sources: [&[u8]; 2] = [b"0123456789ABCDEF", b"GHIJKLMNOPQRSTUV"];
+        let target = b"GHIJKLMNOPQRSTUV0123456789ABCDEF";
+        let patch = Patch::new_multi(&sources, target);
+        assert_eq!(patch.apply_multi(&sources), Some(target.to_vec()));
+        assert_eq!(
+            patch
+                .instructions()
+                .iter()
+                .filter(|instruction| matches!(instruction, DeltaInstruction::OffsetCopy(_)))
+                .count(),
+            2
+        );
+
+        let patch = Patch::new_multi::<&[u8]>(&[], b"literal");
+        assert_eq!(patch.apply_multi::<&[u8]>(&[]), Some(b"literal".to_vec()));
+        assert_eq!(patch, Patch::new(b"", b"literal"));
+    }
+
+    #[test]
+    fn source_boundaries() {
+        let sources: [&[u8]; 2] = [b"0123456789AB", b"CDEFGHIJKLMN"];
+        let target = b"0123456789ABCDEFGHIJKLMN";
+        let patch = Patch::new_multi(&sources, target);
+        assert_eq!(patch.apply_multi(&sources), Some(target.to_vec()));
+        let dictionary = Dictionary::new(&sources);
+        assert_eq!(dictionary.longest_match(target), Some((0, 12)));
+        assert_eq!(dictionary.longest_match(&target[4..]), None);
+        assert_eq!(dictionary.longest_match(&target[12..]), Some((12, 12)));
+
+        let mut builder = PatchBuilder::new();
+        builder.copy_offset(6, 12);
+        let spanning = builder.finish(24);
+        assert_eq!(
+            spanning.apply_multi(&sources),
+            Some(b"6789ABCDEFGH".to_vec())
+        );
+        assert_eq!(spanning.apply_multi(&sources[..1]), None);
+    }
+}
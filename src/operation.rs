@@ -0,0 +1,152 @@
+use alloc::vec::Vec;
+
+use crate::{
+    instructions::{delta_instruction::DeltaInstruction, InstructionInfo},
+    patch::Patch,
+};
+
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) enum Operation {
+    Add(Vec<u8>),
+    Copy { offset: usize, length: usize },
+}
+
+impl Operation {
+    pub fn len(&self) -> usize {
+        match self {
+            Operation::Add(content) => content.len(),
+            Operation::Copy { length, .. } => *length,
+        }
+    }
+
+    pub fn split_off(&mut self, at: usize) -> Self {
+        match self {
+            Operation::Add(content) => Operation::Add(content.split_off(at)),
+            Operation::Copy { offset, length } => {
+                let rest = Operation::Copy {
+                    offset: *offset + at,
+                    length: *length - at,
+                };
+                *length = at;
+                rest
+            }
+        }
+    }
+}
+
+fn push_add(operations: &mut Vec<Operation>, content: &[u8]) {
+    match operations.last_mut() {
+        Some(Operation::Add(last)) => last.extend_from_slice(content),
+        _ => operations.push(Operation::Add(content.to_vec())),
+    }
+}
+
+fn push_copy(operations: &mut Vec<Operation>, offset: usize, length: usize) {
+    match operations.last_mut() {
+        Some(Operation::Copy {
+            offset: last_offset,
+            length: last_length,
+        }) if *last_offset + *last_length == offset => *last_length += length,
+        _ => operations.push(Operation::Copy { offset, length }),
+    }
+}
+
+impl Patch {
+    pub(crate) fn operations(
+        &self,
+        source: &[u8],
+        min_copy_length: usize,
+    ) -> Option<Vec<Operation>> {
+        if source.len() != self.source_lenth() || !self.offsets_fit(source.len()) {
+            return None;
+        }
+        let mut operations: Vec<Operation> = Vec::new();
+        let mut position = 0usize;
+        for instruction in self.instructions().iter() {
+            match instruction {
+                DeltaInstruction::Remove(remove) => position += remove.len() as usize,
+                DeltaInstruction::Add(add) => push_add(&mut operations, add.content()),
+                DeltaInstruction::OffsetCopy(copy) => {
+                    push_copy(&mut operations, copy.offset() as usize, copy.len() as usize)
+                }
+                DeltaInstruction::Copy(copy) => {
+                    let difference = copy.content();
+                    let mut start = 0;
+                    while start < difference.len() {
+                        let zeros = difference[start..]
+                            .iter()
+                            .take_while(|item| **item == 0)
+                            .count();
+                        if zeros >= min_copy_length {
+                            push_copy(&mut operations, position + start, zeros);
+                            start += zeros;
+                            continue;
+                        }
+                        let end = start + zeros.max(1);
+                        let literal: Vec<u8> = (start..end)
+                            .map(|index| source[position + index].wrapping_add(difference[index]))
+                            .collect();
+                        push_add(&mut operations, &literal);
+                        start = end;
+                    }
+                    position += difference.len();
+                }
+            }
+        }
+        Some(operations)
+    }
+}
+
+#[cfg(test)]
+mod operation_tests {
+    use crate::builder::PatchBuilder;
+
+    use super::*;
+
+    #[test]
+    fn operations() {
+        let mut builder = PatchBuilder::new();
+        builder.remove(1);
+        builder.copy(&[0, 0, 0, 0, 0, 1, 0]);
+        builder.copy_offset(8, 2);
+        builder.add(b"Z");
+        builder.copy_offset(0, 2);
+        let patch = builder.finish(10);
+        assert_eq!(
+            patch.operations(b"ABCDEFGHIJ", 4),
+            Some(vec![
+                Operation::Copy {
+                    offset: 1,
+                    length: 5
+                },
+                Operation::Add(b"HH".to_vec()),
+                Operation::Copy {
+                    offset: 8,
+                    length: 2
+                },
+                Operation::Add(b"Z".to_vec()),
+                Operation::Copy {
+                    offset: 0,
+                    length: 2
+                },
+            ])
+        );
+        assert_eq!(patch.operations(b"ABC", 4), None);
+    }
+
+    #[test]
+    fn split_off() {
+        let mut operation = Operation::Copy {
+            offset: 3,
+            length: 5,
+        };
+        assert_eq!(
+            operation.split_off(2),
+            Operation::Copy {
+                offset: 5,
+                length: 3
+            }
+        );
+        assert_eq!(operation.len(), 2);
+    }
+}
@@ -0,0 +1,375 @@
+use alloc::{vec, vec::Vec};
+
+use crate::{
+    container::Compression,
+    instructions::{InstructionError, Result},
+    patch::Patch,
+};
+
+const PACK_MAGIC: &[u8; 4] = b"DLTP";
+const PACK_VERSION: u8 = 1;
+const PACK_HEADER_LENGTH: usize = PACK_MAGIC.len() + 2 + 8;
+const FULL_SIGN: u8 = b'F';
+const DELTA_SIGN: u8 = b'D';
+const NUMBER_LENGTH: usize = core::mem::size_of::<u64>();
+const DEFAULT_WINDOW: usize = 10;
+const DEFAULT_MAX_DEPTH: usize = 50;
+
+fn read_number(bytes: &[u8], offset: usize) -> Result<usize> {
+    let field = offset
+        .checked_add(NUMBER_LENGTH)
+        .and_then(|end| bytes.get(offset..end))
+        .ok_or(InstructionError::MissingContent)?;
+    usize::try_from(u64::from_be_bytes(field.try_into().unwrap()))
+        .map_err(|_| InstructionError::InvalidLength)
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PackBuilder {
+    window: usize,
+    max_depth: usize,
+    compression: Compression,
+}
+
+impl PackBuilder {
+    pub fn new() -> Self {
+        Self {
+            window: DEFAULT_WINDOW,
+            max_depth: DEFAULT_MAX_DEPTH,
+            compression: Compression::None,
+        }
+    }
+
+    pub fn window(mut self, window: usize) -> Self {
+        self.window = window;
+        self
+    }
+
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    pub fn compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    fn delta_bytes(&self, patch: &Patch) -> Vec<u8> {
+        if self.compression == Compression::None {
+            patch.to_bytes()
+        } else {
+            patch.to_compressed_bytes(self.compression)
+        }
+    }
+
+    fn choose_bases<B: AsRef<[u8]>>(&self, blobs: &[B]) -> Vec<Option<(usize, Vec<u8>)>> {
+        let mut order: Vec<usize> = (0..blobs.len()).collect();
+        order.sort_by_key(|index| core::cmp::Reverse(blobs[*index].as_ref().len()));
+        let mut depths = vec![0usize; blobs.len()];
+        let mut bases: Vec<Option<(usize, Vec<u8>)>> = (0..blobs.len()).map(|_| None).collect();
+        for (position, index) in order.iter().copied().enumerate() {
+            let blob = blobs[index].as_ref();
+            let mut best: Option<(usize, Vec<u8>)> = None;
+            for base in order[position.saturating_sub(self.window)..position]
+                .iter()
+                .copied()
+                .filter(|base| depths[*base] < self.max_depth)
+            {
+                let delta = self.delta_bytes(&Patch::new(blobs[base].as_ref(), blob));
+                if delta.len() < blob.len()
+                    && best.as_ref().is_none_or(|best| delta.len() < best.1.len())
+                {
+                    best = Some((base, delta));
+                }
+            }
+            if let Some((base, delta)) = best {
+                depths[index] = depths[base] + 1;
+                bases[index] = Some((base, delta));
+            }
+        }
+        bases
+    }
+
+    pub fn build<B: AsRef<[u8]>>(&self, blobs: &[B]) -> Vec<u8> {
+        let mut bytes: Vec<u8> = Vec::new();
+        bytes.extend_from_slice(PACK_MAGIC);
+        bytes.push(PACK_VERSION);
+        bytes.push(self.compression.byte_sign());
+        bytes.extend((blobs.len() as u64).to_be_bytes());
+        let mut offsets: Vec<usize> = Vec::with_capacity(blobs.len());
+        for (blob, base) in blobs.iter().zip(self.choose_bases(blobs)) {
+            offsets.push(bytes.len());
+            let content = match base {
+                Some((base, delta)) => {
+                    bytes.push(DELTA_SIGN);
+                    bytes.extend((base as u64).to_be_bytes());
+                    delta
+                }
+                None => {
+                    bytes.push(FULL_SIGN);
+                    blob.as_ref().to_vec()
+                }
+            };
+            bytes.extend((content.len() as u64).to_be_bytes());
+            bytes.extend_from_slice(&content);
+        }
+        let index_offset = bytes.len();
+        for offset in offsets {
+            bytes.extend((offset as u64).to_be_bytes());
+        }
+        bytes.extend((index_offset as u64).to_be_bytes());
+        bytes
+    }
+}
+
+impl Default for PackBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+enum PackEntry<'a> {
+    Full(&'a [u8]),
+    Delta { base: usize, patch: &'a [u8] },
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PackView<'a> {
+    bytes: &'a [u8],
+    index_offset: usize,
+    len: usize,
+}
+
+impl<'a> PackView<'a> {
+    pub fn new(bytes: &'a [u8]) -> Result<Self> {
+        if bytes.len() < PACK_HEADER_LENGTH + NUMBER_LENGTH
+            || !bytes.starts_with(PACK_MAGIC)
+            || bytes[PACK_MAGIC.len()] != PACK_VERSION
+        {
+            return Err(InstructionError::InvalidHeader);
+        }
+        Compression::try_from_byte_sign(bytes[PACK_MAGIC.len() + 1])?;
+        let len = read_number(bytes, PACK_MAGIC.len() + 2)?;
+        let index_offset = read_number(bytes, bytes.len() - NUMBER_LENGTH)?;
+        let index_end = len
+            .checked_mul(NUMBER_LENGTH)
+            .and_then(|length| index_offset.checked_add(length))
+            .ok_or(InstructionError::InvalidHeader)?;
+        if index_offset < PACK_HEADER_LENGTH || index_end != bytes.len() - NUMBER_LENGTH {
+            return Err(InstructionError::InvalidHeader);
+        }
+        Ok(Self {
+            bytes,
+            index_offset,
+            len,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn entry(&self, index: usize) -> Result<PackEntry<'a>> {
+        if index >= self.len {
+            return Err(InstructionError::IndexOutOfRange {
+                index,
+                length: self.len,
+            });
+        }
+        let entries = &self.bytes[..self.index_offset];
+        let offset = read_number(self.bytes, self.index_offset + index * NUMBER_LENGTH)?;
+        let sign = *entries
+            .get(offset)
+            .ok_or(InstructionError::MissingContent)?;
+        let (base, offset) = match sign {
+            FULL_SIGN => (None, offset + 1),
+            DELTA_SIGN => (
+                Some(read_number(entries, offset + 1)?),
+                offset + 1 + NUMBER_LENGTH,
+            ),
+            _ => return Err(InstructionError::InvalidSign),
+        };
+        let length = read_number(entries, offset)?;
+        let content = (offset + NUMBER_LENGTH)
+            .checked_add(length)
+            .and_then(|end| entries.get(offset + NUMBER_LENGTH..end))
+            .ok_or(InstructionError::MissingContent)?;
+        Ok(match base {
+            Some(base) => PackEntry::Delta {
+                base,
+                patch: content,
+            },
+            None => PackEntry::Full(content),
+        })
+    }
+
+    pub fn base(&self, index: usize) -> Result<Option<usize>> {
+        Ok(match self.entry(index)? {
+            PackEntry::Full(_) => None,
+            PackEntry::Delta { base, .. } => Some(base),
+        })
+    }
+
+    pub fn depth(&self, index: usize) -> Result<usize> {
+        let mut depth = 0;
+        let mut index = index;
+        while let Some(base) = self.base(index)? {
+            depth += 1;
+            if depth > self.len {
+                return Err(InstructionError::InvalidContent);
+            }
+            index = base;
+        }
+        Ok(depth)
+    }
+
+    pub fn get(&self, index: usize) -> Result<Vec<u8>> {
+        let mut patches: Vec<&[u8]> = Vec::new();
+        let mut index = index;
+        let mut content = loop {
+            match self.entry(index)? {
+                PackEntry::Full(content) => break content.to_vec(),
+                PackEntry::Delta { base, patch } => {
+                    if patches.len() >= self.len {
+                        return Err(InstructionError::InvalidContent);
+                    }
+                    patches.push(patch);
+                    index = base;
+                }
+            }
+        };
+        for patch in patches.iter().rev() {
+            content = Patch::try_from_bytes(patch)?
+                .apply(&content)
+                .ok_or(InstructionError::SourceMismatch)?;
+        }
+        Ok(content)
+    }
+}
+
+impl<'a> TryFrom<&'a [u8]> for PackView<'a> {
+    type Error = InstructionError;
+
+    fn try_from(bytes: &'a [u8]) -> Result<Self> {
+        Self::new(bytes)
+    }
+}
+
+#[cfg(test)]
+mod pack_tests {
+    use std::fs;
+
+    use super::*;
+
+    fn builds() -> Vec<Vec<u8>> {
+        let mut content = fs::read("files/source.txt").unwrap()[..600].to_vec();
+        let mut builds = vec![content.clone()];
+        for index in 0..6u8 {
+            content[index as usize * 11] = b'a' + index;
+            content.extend_from_slice(&[b'0' + index; 4]);
+            builds.push(content.clone());
+        }
+        builds.push(b"unrelated blob".to_vec());
+        builds
+    }
+
+    #[test]
+    fn build_and_get() {
+        let builds = builds();
+        let bytes = PackBuilder::new().build(&builds);
+        let pack = PackView::new(&bytes).unwrap();
+        assert_eq!(pack.len(), builds.len());
+        for (index, build) in builds.iter().enumerate() {
+            assert_eq!(&pack.get(index).unwrap(), build);
+        }
+        assert_eq!(pack.base(6).unwrap(), None);
+        assert_eq!(pack.base(7).unwrap(), None);
+        assert_eq!(
+            pack.get(builds.len()),
+            Err(InstructionError::IndexOutOfRange {
+                index: builds.len(),
+                length: builds.len()
+            })
+        );
+
+        let full = PackBuilder::new().window(0).build(&builds);
+        let pack = PackView::new(&full).unwrap();
+        assert!((0..pack.len()).all(|index| pack.base(index).unwrap().is_none()));
+        assert_eq!(
+            full.len(),
+            builds.iter().map(|build| build.len() + 17).sum::<usize>() + 22
+        );
+        assert!(bytes.len() <= full.len());
+
+        let empty = PackBuilder::new().build::<&[u8]>(&[]);
+        assert!(PackView::new(&empty).unwrap().is_empty());
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn window_and_depth() {
+        let builds = builds();
+        let bytes = PackBuilder::new()
+            .compression(Compression::Zstd)
+            .max_depth(2)
+            .build(&builds);
+        let pack = PackView::new(&bytes).unwrap();
+        assert!((0..pack.len()).all(|index| pack.depth(index).unwrap() <= 2));
+        assert!((0..pack.len()).any(|index| pack.depth(index).unwrap() == 2));
+        assert!(pack.base(0).unwrap().is_some());
+        for (index, build) in builds.iter().enumerate() {
+            assert_eq!(&pack.get(index).unwrap(), build);
+        }
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn compressed() {
+        let builds = builds();
+        let bytes = PackBuilder::new()
+            .compression(Compression::Zstd)
+            .build(&builds);
+        assert!(bytes.len() < PackBuilder::new().build(&builds).len());
+        let pack = PackView::new(&bytes).unwrap();
+        for (index, build) in builds.iter().enumerate() {
+            assert_eq!(&pack.get(index).unwrap(), build);
+        }
+    }
+
+    #[test]
+    fn new_err() {
+        let bytes = PackBuilder::new().build(&builds());
+        assert_eq!(
+            PackView::new(&bytes[..PACK_HEADER_LENGTH]).unwrap_err(),
+            InstructionError::InvalidHeader
+        );
+        assert_eq!(
+            PackView::new(&bytes[..bytes.len() - 1]).unwrap_err(),
+            InstructionError::InvalidHeader
+        );
+        let mut unknown_compression = bytes.clone();
+        unknown_compression[PACK_MAGIC.len() + 1] = u8::MAX;
+        assert_eq!(
+            PackView::new(&unknown_compression).unwrap_err(),
+            InstructionError::UnsupportedCompression
+        );
+
+        let mut cycle = PackBuilder::new().build(&[b"A"]);
+        cycle[PACK_HEADER_LENGTH] = DELTA_SIGN;
+        cycle.splice(
+            PACK_HEADER_LENGTH + 1..PACK_HEADER_LENGTH + 1,
+            0u64.to_be_bytes(),
+        );
+        let index_offset = cycle.len() - 2 * NUMBER_LENGTH;
+        let length = cycle.len();
+        cycle[length - NUMBER_LENGTH..].copy_from_slice(&(index_offset as u64).to_be_bytes());
+        let pack = PackView::new(&cycle).unwrap();
+        assert_eq!(pack.get(0), Err(InstructionError::InvalidContent));
+        assert_eq!(pack.depth(0), Err(InstructionError::InvalidContent));
+    }
+}
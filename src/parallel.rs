@@ -0,0 +1,202 @@
+use alloc::{collections::BTreeMap, vec, vec::Vec};
+use core::ops::Range;
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use crate::{
+    builder::PatchBuilder,
+    chunking::{chunk_hash, Chunker, MAX_LCS_CELLS},
+    patch::Patch,
+};
+
+const ANCHOR_CHUNKER: Chunker = Chunker::new(64, 256, 1024);
+
+#[derive(Debug, Default, Clone, Copy)]
+struct Occurrences {
+    source: Option<usize>,
+    source_count: usize,
+    target: Option<usize>,
+    target_count: usize,
+}
+
+#[derive(Debug, Clone)]
+struct Segment {
+    source: Range<usize>,
+    target: Range<usize>,
+}
+
+fn anchors(source: &[u8], target: &[u8]) -> Vec<(usize, usize, usize)> {
+    let mut occurrences: BTreeMap<u64, Occurrences> = BTreeMap::new();
+    let mut offset = 0;
+    for chunk in ANCHOR_CHUNKER.split(source) {
+        let entry = occurrences.entry(chunk_hash(chunk)).or_default();
+        entry.source.get_or_insert(offset);
+        entry.source_count += 1;
+        offset += chunk.len();
+    }
+    let mut offset = 0;
+    for chunk in ANCHOR_CHUNKER.split(target) {
+        if let Some(entry) = occurrences.get_mut(&chunk_hash(chunk)) {
+            entry.target.get_or_insert(offset);
+            entry.target_count += 1;
+        }
+        offset += chunk.len();
+    }
+
+    let mut candidates: Vec<(usize, usize, usize)> = Vec::new();
+    let mut offset = 0;
+    for chunk in ANCHOR_CHUNKER.split(target) {
+        let length = chunk.len();
+        if let Some(Occurrences {
+            source: Some(source_offset),
+            source_count: 1,
+            target: Some(target_offset),
+            target_count: 1,
+        }) = occurrences.get(&chunk_hash(chunk)).copied()
+        {
+            if target_offset == offset
+                && source.get(source_offset..source_offset + length) == Some(chunk)
+            {
+                candidates.push((source_offset, target_offset, length));
+            }
+        }
+        offset += length;
+    }
+
+    let mut anchors: Vec<(usize, usize, usize)> = Vec::with_capacity(candidates.len());
+    for candidate in candidates {
+        let source_end = anchors
+            .last()
+            .map_or(0, |(offset, _, length)| offset + length);
+        if candidate.0 >= source_end {
+            anchors.push(candidate);
+        }
+    }
+    anchors
+}
+
+fn segment_patch(source: &[u8], target: &[u8], segment: &Segment) -> Patch {
+    let source = &source[segment.source.clone()];
+    let target = &target[segment.target.clone()];
+    if source.len().saturating_mul(target.len()) <= MAX_LCS_CELLS {
+        Patch::new(source, target)
+    } else {
+        Patch::new_multi(&[source], target)
+    }
+}
+
+impl Patch {
+    pub fn new_parallel(source: &[u8], target: &[u8]) -> Self {
+        let anchors = anchors(source, target);
+        let mut segments: Vec<Segment> = Vec::with_capacity(anchors.len() + 1);
+        let (mut source_start, mut target_start) = (0, 0);
+        for (source_offset, target_offset, length) in anchors.iter().copied() {
+            segments.push(Segment {
+                source: source_start..source_offset,
+                target: target_start..target_offset,
+            });
+            source_start = source_offset + length;
+            target_start = target_offset + length;
+        }
+        segments.push(Segment {
+            source: source_start..source.len(),
+            target: target_start..target.len(),
+        });
+
+        #[cfg(feature = "rayon")]
+        let patches: Vec<Patch> = segments
+            .par_iter()
+            .map(|segment| segment_patch(source, target, segment))
+            .collect();
+        #[cfg(not(feature = "rayon"))]
+        let patches: Vec<Patch> = segments
+            .iter()
+            .map(|segment| segment_patch(source, target, segment))
+            .collect();
+
+        let mut builder = PatchBuilder::new();
+        for (patch, (_, _, length)) in patches.iter().zip(anchors.iter()) {
+            builder.append(patch);
+            builder.copy(&vec![0; *length]);
+        }
+        if let Some(patch) = patches.last() {
+            builder.append(patch);
+        }
+        builder.finish(source.len())
+    }
+}
+
+#[cfg(test)]
+mod parallel_tests {
+    use std::fs;
+
+    use super::*;
+
+    fn numbered() -> Vec<u8> {
+        let source = fs::read("files/source.txt").unwrap();
+        let mut numbered = Vec::new();
+        for index in 0..3 {
+            for (line, content) in source.split_inclusive(|item| *item == b'\n').enumerate() {
+                numbered.extend_from_slice(format!("{index}.{line}: ").as_bytes());
+                numbered.extend_from_slice(content);
+            }
+        }
+        numbered
+    }
+
+    fn edited(source: &[u8]) -> Vec<u8> {
+        let mut target = source.to_vec();
+        target.splice(500..500, b"inserted line\n".iter().copied());
+        target[4_000] = b'#';
+        target.drain(6_000..6_300);
+        target.extend_from_slice(&source[..700]);
+        target
+    }
+
+    #[test]
+    fn new_parallel() {
+        let source = numbered();
+        let target = edited(&source);
+        let patch = Patch::new_parallel(&source, &target);
+        assert_eq!(patch.apply(&source), Some(target.clone()));
+        assert!(anchors(&source, &target).len() > 10);
+        assert!(patch.to_bytes().len() < target.len() + target.len() / 10);
+        let decoded = Patch::try_from_bytes(&patch.to_bytes()).unwrap();
+        assert_eq!(decoded.apply(&source), Some(target));
+
+        let source = fs::read("files/source.txt").unwrap();
+        let target = fs::read("files/target.txt").unwrap();
+        assert_eq!(
+            Patch::new_parallel(&source, &target).apply(&source),
+            Some(target.clone())
+        );
+        assert_eq!(Patch::new_parallel(b"", b"").apply(b""), Some(Vec::new()));
+        assert_eq!(
+            Patch::new_parallel(&source, b"").apply(&source),
+            Some(Vec::new())
+        );
+        assert_eq!(Patch::new_parallel(b"", &target).apply(b""), Some(target));
+    }
+
+    #[test]
+    fn anchors_are_ordered() {
+        let source = numbered();
+        let mut target = source[4_000..].to_vec();
+        target.extend_from_slice(&source[..4_000]);
+        let anchors = anchors(&source, &target);
+        assert!(anchors
+            .windows(2)
+            .all(|pair| pair[0].0 + pair[0].2 <= pair[1].0 && pair[0].1 + pair[0].2 <= pair[1].1));
+        for (source_offset, target_offset, length) in anchors {
+            assert_eq!(
+                source[source_offset..source_offset + length],
+                target[target_offset..target_offset + length]
+            );
+        }
+        assert_eq!(
+            Patch::new_parallel(&source, &target).apply(&source),
+            Some(target)
+        );
+    }
+}
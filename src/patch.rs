@@ -1,17 +1,21 @@
-use std::{iter::Peekable, slice::Iter};
+use alloc::vec::Vec;
+use core::{iter::Peekable, slice::Iter};
 
 use crate::{
+    container::{self, Compression},
     instructions::{
         add_instruction::AddInstruction, copy_instruction::CopyInstruction,
         delta_instruction::DeltaInstruction, remove_instruction::RemoveInstruction,
         InstructionBytes, InstructionContent, InstructionError, InstructionInfo, Result,
     },
     lcs::Lcs,
+    seek::SeekIndex,
 };
 
-#[derive(Debug, Default, PartialEq, Clone)]
+#[derive(Debug, Default, Clone)]
 pub struct Patch {
     instructions: Vec<DeltaInstruction>,
+    seek_index: Option<SeekIndex>,
 }
 
 impl Patch {
@@ -26,9 +30,30 @@ impl Patch {
                 &mut source_iter,
                 &mut target_iter,
             ),
+            seek_index: None,
         }
     }
 
+    pub(crate) fn from_instructions(instructions: Vec<DeltaInstruction>) -> Self {
+        Self {
+            instructions,
+            seek_index: None,
+        }
+    }
+
+    pub(crate) fn instructions(&self) -> &[DeltaInstruction] {
+        &self.instructions
+    }
+
+    pub fn with_seek_index(mut self, interval: usize) -> Self {
+        self.seek_index = Some(SeekIndex::new(&self, interval));
+        self
+    }
+
+    pub fn seek_index(&self) -> Option<&SeekIndex> {
+        self.seek_index.as_ref()
+    }
+
     fn create_instructions(
         lcs: &mut Peekable<Iter<'_, u8>>,
         source: &mut Peekable<Iter<'_, u8>>,
@@ -65,38 +90,47 @@ impl Patch {
     }
 
     pub fn apply(&self, source: &[u8]) -> Option<Vec<u8>> {
-        let mut source_iter = source.iter();
-        self.construct_target(&mut source_iter)
+        self.construct_target(source)
     }
 
-    fn construct_target(&self, source: &mut Iter<'_, u8>) -> Option<Vec<u8>> {
-        if source.len() != self.source_lenth() {
+    fn construct_target(&self, source: &[u8]) -> Option<Vec<u8>> {
+        if source.len() != self.source_lenth() || !self.offsets_fit(source.len()) {
             return None;
         }
         let mut target: Vec<u8> = Vec::with_capacity(self.target_length());
+        let mut position = 0usize;
         for instruction in self.instructions.iter() {
-            instruction.apply(source, &mut target)
+            instruction.apply(source, &mut position, &mut target)
         }
         Some(target)
     }
 
-    fn target_length(&self) -> usize {
+    pub(crate) fn offsets_fit(&self, source_length: usize) -> bool {
+        self.instructions
+            .iter()
+            .all(|instruction| match instruction {
+                DeltaInstruction::OffsetCopy(instruction) => instruction
+                    .end()
+                    .is_some_and(|end| end <= source_length as u64),
+                _ => true,
+            })
+    }
+
+    pub(crate) fn target_length(&self) -> usize {
         self.instructions
             .iter()
             .fold(0usize, |mut acc, instruction| {
                 match instruction {
                     DeltaInstruction::Remove(_) => (),
                     DeltaInstruction::Add(_) => acc += instruction.len() as usize,
-                    DeltaInstruction::Copy(_) => {
-                        acc += instruction.len() as usize
-                            - instruction.non_default_item_count().unwrap() as usize
-                    }
+                    DeltaInstruction::Copy(_) => acc += instruction.len() as usize,
+                    DeltaInstruction::OffsetCopy(_) => acc += instruction.len() as usize,
                 };
                 acc
             })
     }
 
-    fn source_lenth(&self) -> usize {
+    pub(crate) fn source_lenth(&self) -> usize {
         self.instructions
             .iter()
             .fold(0usize, |mut acc, instruction| {
@@ -104,6 +138,7 @@ impl Patch {
                     DeltaInstruction::Remove(_) => acc += instruction.len() as usize,
                     DeltaInstruction::Add(_) => (),
                     DeltaInstruction::Copy(_) => acc += instruction.len() as usize,
+                    DeltaInstruction::OffsetCopy(_) => (),
                 };
                 acc
             })
@@ -124,13 +159,33 @@ impl Patch {
         bytes
     }
 
+    pub fn to_compressed_bytes(&self, compression: Compression) -> Vec<u8> {
+        container::to_bytes(self, compression)
+    }
+
     pub fn try_from_bytes(bytes: &[u8]) -> Result<Self> {
+        if container::is_container(bytes) {
+            return Self::try_from_raw_bytes(&container::to_raw_bytes(bytes)?);
+        }
+        Self::try_from_raw_bytes(bytes)
+    }
+
+    fn try_from_raw_bytes(bytes: &[u8]) -> Result<Self> {
         let mut bytes_iter = bytes.iter().peekable();
         let mut instructions: Vec<DeltaInstruction> = Vec::new();
-        while bytes_iter.peek().is_some() {
-            instructions.push(DeltaInstruction::try_from_bytes(&mut bytes_iter)?);
+        while let Some(byte) = bytes_iter.peek().copied() {
+            let offset = bytes.len() - bytes_iter.len();
+            let instruction = DeltaInstruction::try_from_bytes(&mut bytes_iter)
+                .map_err(|error| error.at(instructions.len(), offset, *byte))?;
+            instructions.push(instruction);
         }
-        Ok(Self { instructions })
+        Ok(Self::from_instructions(instructions))
+    }
+}
+
+impl PartialEq for Patch {
+    fn eq(&self, other: &Self) -> bool {
+        self.instructions == other.instructions
     }
 }
 
@@ -149,7 +204,7 @@ impl From<Patch> for Vec<u8> {
 impl TryFrom<&[u8]> for Patch {
     type Error = InstructionError;
 
-    fn try_from(value: &[u8]) -> std::result::Result<Self, Self::Error> {
+    fn try_from(value: &[u8]) -> core::result::Result<Self, Self::Error> {
         Patch::try_from_bytes(value)
     }
 }
@@ -157,7 +212,7 @@ impl TryFrom<&[u8]> for Patch {
 impl TryFrom<Vec<u8>> for Patch {
     type Error = InstructionError;
 
-    fn try_from(value: Vec<u8>) -> std::result::Result<Self, Self::Error> {
+    fn try_from(value: Vec<u8>) -> core::result::Result<Self, Self::Error> {
         Patch::try_from_bytes(&value)
     }
 }
@@ -166,6 +221,8 @@ impl TryFrom<Vec<u8>> for Patch {
 mod remove_instruction_tests {
     use std::fs;
 
+    use crate::instructions::offset_copy_instruction::OffsetCopyInstruction;
+
     use super::*;
 
     #[test]
@@ -190,6 +247,15 @@ mod remove_instruction_tests {
         assert_eq!(Patch::new(b"", b"AAA").target_length(), 3);
     }
 
+    #[test]
+    fn target_length_with_differences() {
+        let patch = Patch::from_instructions(vec![CopyInstruction::new(vec![0, 1, 0, 2]).into()]);
+        assert_eq!(patch.target_length(), 4);
+        assert_eq!(patch.apply(b"AAAA"), Some(b"ABAC".to_vec()));
+        let patch = Patch::new(b"ABCDEFGH", b"ABXDEFYH");
+        assert_eq!(patch.target_length(), 8);
+    }
+
     #[test]
     fn source_length() {
         assert_eq!(Patch::new(b"AAA", b"AAA").source_lenth(), 3);
@@ -231,6 +297,20 @@ mod remove_instruction_tests {
         }
     }
 
+    #[test]
+    fn apply_offset_copy() {
+        let patch = Patch::from_instructions(vec![
+            OffsetCopyInstruction::new(1, 2).into(),
+            CopyInstruction::new(vec![0, 0, 0]).into(),
+        ]);
+        assert_eq!(patch.apply(b"ABC"), Some(b"BCABC".to_vec()));
+        assert_eq!(patch.source_lenth(), 3);
+        assert_eq!(patch.target_length(), 5);
+
+        let patch = Patch::from_instructions(vec![OffsetCopyInstruction::new(2, 2).into()]);
+        assert_eq!(patch.apply(b""), None);
+    }
+
     #[test]
     fn try_from_bytes() {
         let source = fs::read("files/source.txt").unwrap();
@@ -240,4 +320,23 @@ mod remove_instruction_tests {
         let constructed_patch = Patch::try_from_bytes(&patch_bytes).unwrap();
         assert_eq!(patch, constructed_patch);
     }
+
+    #[test]
+    fn try_from_bytes_err() {
+        assert_eq!(
+            Patch::try_from_bytes(b"-\x01+\x01A?\x01"),
+            Err(InstructionError::InvalidSign.at(2, 5, b'?'))
+        );
+        assert_eq!(
+            Patch::try_from_bytes(b"|\x03\x00"),
+            Err(InstructionError::MissingContent.at(0, 0, b'|'))
+        );
+        let mut overflow = b"@\x03".to_vec();
+        overflow.extend([u8::MAX; 8]);
+        overflow.extend(b"|\x03\x00\x00\x00");
+        assert_eq!(
+            Patch::try_from_bytes(&overflow),
+            Err(InstructionError::InvalidContent.at(0, 0, b'@'))
+        );
+    }
 }
@@ -0,0 +1,613 @@
+use alloc::{vec, vec::Vec};
+
+use crate::{
+    builder::PatchBuilder,
+    crc32::crc32,
+    instructions::{InstructionError, Result},
+    operation::Operation,
+    patch::Patch,
+};
+
+const IPS_MAGIC: &[u8; 5] = b"PATCH";
+const IPS_FOOTER: &[u8; 3] = b"EOF";
+const IPS_MAX_OFFSET: usize = 0xFFFFFF;
+const IPS_MAX_RECORD_LENGTH: usize = 0xFFFF;
+const IPS_RECORD_GAP: usize = 6;
+
+const UPS_MAGIC: &[u8; 4] = b"UPS1";
+const BPS_MAGIC: &[u8; 4] = b"BPS1";
+const FOOTER_LENGTH: usize = 12;
+const MAX_TARGET_LENGTH: usize = 1 << 30;
+
+const BPS_SOURCE_READ: usize = 0;
+const BPS_TARGET_READ: usize = 1;
+const BPS_SOURCE_COPY: usize = 2;
+const BPS_TARGET_COPY: usize = 3;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct RomPatch {
+    patch: Patch,
+    source_checksum: Option<u32>,
+    target_checksum: Option<u32>,
+}
+
+fn write_number(bytes: &mut Vec<u8>, mut value: usize) {
+    loop {
+        let item = (value & 0x7F) as u8;
+        value >>= 7;
+        if value == 0 {
+            bytes.push(0x80 | item);
+            return;
+        }
+        bytes.push(item);
+        value -= 1;
+    }
+}
+
+fn read_number(bytes: &mut core::slice::Iter<'_, u8>) -> Result<usize> {
+    let mut value = 0usize;
+    let mut shift = 1usize;
+    loop {
+        let item = *bytes.next().ok_or(InstructionError::MissingLength)?;
+        value = ((item & 0x7F) as usize)
+            .checked_mul(shift)
+            .and_then(|part| value.checked_add(part))
+            .ok_or(InstructionError::InvalidLength)?;
+        if item & 0x80 != 0 {
+            return Ok(value);
+        }
+        shift = shift
+            .checked_mul(0x80)
+            .ok_or(InstructionError::InvalidLength)?;
+        value = value
+            .checked_add(shift)
+            .ok_or(InstructionError::InvalidLength)?;
+    }
+}
+
+fn read_bytes<'a>(bytes: &mut core::slice::Iter<'a, u8>, length: usize) -> Result<&'a [u8]> {
+    let remaining = bytes.as_slice();
+    if remaining.len() < length {
+        return Err(InstructionError::MissingContent);
+    }
+    if length > 0 {
+        bytes.nth(length - 1);
+    }
+    Ok(&remaining[..length])
+}
+
+fn read_checksum(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes(bytes.try_into().unwrap())
+}
+
+fn split_footer<'a>(bytes: &'a [u8], magic: &[u8]) -> Result<(&'a [u8], u32, u32)> {
+    if bytes.len() < magic.len() + FOOTER_LENGTH || !bytes.starts_with(magic) {
+        return Err(InstructionError::InvalidHeader);
+    }
+    let (body, footer) = bytes.split_at(bytes.len() - FOOTER_LENGTH);
+    if crc32(&bytes[..bytes.len() - 4]) != read_checksum(&footer[8..12]) {
+        return Err(InstructionError::InvalidChecksum);
+    }
+    Ok((
+        &body[magic.len()..],
+        read_checksum(&footer[0..4]),
+        read_checksum(&footer[4..8]),
+    ))
+}
+
+fn push_footer(bytes: &mut Vec<u8>, source: &[u8], target: &[u8]) {
+    bytes.extend(crc32(source).to_le_bytes());
+    bytes.extend(crc32(target).to_le_bytes());
+    bytes.extend(crc32(bytes).to_le_bytes());
+}
+
+fn positional_patch(source: &[u8], target: &[u8]) -> Patch {
+    let mut builder = PatchBuilder::new();
+    let difference: Vec<u8> = source
+        .iter()
+        .zip(target.iter())
+        .map(|(source_item, target_item)| target_item.wrapping_sub(*source_item))
+        .collect();
+    builder.copy(&difference);
+    builder.add(target.get(source.len()..).unwrap_or_default());
+    builder.finish(source.len())
+}
+
+impl RomPatch {
+    pub fn new(patch: Patch, source: &[u8]) -> Option<Self> {
+        let target = patch.apply(source)?;
+        Some(Self {
+            patch,
+            source_checksum: Some(crc32(source)),
+            target_checksum: Some(crc32(&target)),
+        })
+    }
+
+    pub fn patch(&self) -> &Patch {
+        &self.patch
+    }
+
+    pub fn source_checksum(&self) -> Option<u32> {
+        self.source_checksum
+    }
+
+    pub fn target_checksum(&self) -> Option<u32> {
+        self.target_checksum
+    }
+
+    pub fn apply(&self, source: &[u8]) -> Result<Vec<u8>> {
+        if self
+            .source_checksum
+            .is_some_and(|checksum| checksum != crc32(source))
+        {
+            return Err(InstructionError::SourceMismatch);
+        }
+        let target = self
+            .patch
+            .apply(source)
+            .ok_or(InstructionError::SourceMismatch)?;
+        if self
+            .target_checksum
+            .is_some_and(|checksum| checksum != crc32(&target))
+        {
+            return Err(InstructionError::InvalidChecksum);
+        }
+        Ok(target)
+    }
+
+    pub fn from_ips(bytes: &[u8], source: &[u8]) -> Result<Self> {
+        if !bytes.starts_with(IPS_MAGIC) {
+            return Err(InstructionError::InvalidHeader);
+        }
+        let mut bytes = bytes[IPS_MAGIC.len()..].iter();
+        let mut target = source.to_vec();
+        loop {
+            let offset = read_bytes(&mut bytes, 3)?;
+            if offset == IPS_FOOTER {
+                break;
+            }
+            let offset = u32::from_be_bytes([0, offset[0], offset[1], offset[2]]) as usize;
+            let length = read_bytes(&mut bytes, 2)?;
+            let mut length = u16::from_be_bytes([length[0], length[1]]) as usize;
+            let run = if length == 0 {
+                let run_length = read_bytes(&mut bytes, 2)?;
+                length = u16::from_be_bytes([run_length[0], run_length[1]]) as usize;
+                Some(*read_bytes(&mut bytes, 1)?.first().unwrap())
+            } else {
+                None
+            };
+            if target.len() < offset + length {
+                target.resize(offset + length, 0);
+            }
+            match run {
+                Some(item) => target[offset..offset + length].fill(item),
+                None => {
+                    target[offset..offset + length].copy_from_slice(read_bytes(&mut bytes, length)?)
+                }
+            }
+        }
+        match bytes.as_slice().len() {
+            0 => (),
+            3 => {
+                let length = read_bytes(&mut bytes, 3)?;
+                target.truncate(u32::from_be_bytes([0, length[0], length[1], length[2]]) as usize);
+            }
+            _ => return Err(InstructionError::InvalidContent),
+        }
+        Ok(Self {
+            patch: positional_patch(source, &target),
+            source_checksum: None,
+            target_checksum: None,
+        })
+    }
+
+    pub fn from_ups(bytes: &[u8], source: &[u8]) -> Result<Self> {
+        let (body, source_checksum, target_checksum) = split_footer(bytes, UPS_MAGIC)?;
+        let mut body = body.iter();
+        let source_length = read_number(&mut body)?;
+        let target_length = read_number(&mut body)?;
+        if target_length > MAX_TARGET_LENGTH {
+            return Err(InstructionError::InvalidLength);
+        }
+        if source_length != source.len() || source_checksum != crc32(source) {
+            return Err(InstructionError::SourceMismatch);
+        }
+
+        let mut target = source[..source.len().min(target_length)].to_vec();
+        let mut position = 0usize;
+        while body.len() > 0 {
+            position = position
+                .checked_add(read_number(&mut body)?)
+                .ok_or(InstructionError::InvalidLength)?;
+            loop {
+                let item = *body.next().ok_or(InstructionError::MissingContent)?;
+                if position < target_length {
+                    if position >= target.len() {
+                        target
+                            .try_reserve(position + 1 - target.len())
+                            .map_err(|_| InstructionError::InvalidLength)?;
+                        target.resize(position + 1, 0);
+                    }
+                    target[position] ^= item;
+                }
+                position += 1;
+                if item == 0 {
+                    break;
+                }
+            }
+        }
+        target
+            .try_reserve_exact(target_length - target.len())
+            .map_err(|_| InstructionError::InvalidLength)?;
+        target.resize(target_length, 0);
+        Ok(Self {
+            patch: positional_patch(source, &target),
+            source_checksum: Some(source_checksum),
+            target_checksum: Some(target_checksum),
+        })
+    }
+
+    pub fn from_bps(bytes: &[u8], source: &[u8]) -> Result<Self> {
+        let (body, source_checksum, target_checksum) = split_footer(bytes, BPS_MAGIC)?;
+        let mut body = body.iter();
+        let source_length = read_number(&mut body)?;
+        let target_length = read_number(&mut body)?;
+        if target_length > MAX_TARGET_LENGTH {
+            return Err(InstructionError::InvalidLength);
+        }
+        let metadata_length = read_number(&mut body)?;
+        read_bytes(&mut body, metadata_length)?;
+        if source_length != source.len() || source_checksum != crc32(source) {
+            return Err(InstructionError::SourceMismatch);
+        }
+
+        let mut builder = PatchBuilder::new();
+        let mut target: Vec<u8> = Vec::new();
+        let mut source_offset = 0usize;
+        let mut target_offset = 0usize;
+        while body.len() > 0 {
+            let action = read_number(&mut body)?;
+            let length = (action >> 2) + 1;
+            if length > target_length - target.len() {
+                return Err(InstructionError::InvalidContent);
+            }
+            match action & 3 {
+                BPS_SOURCE_READ => {
+                    let offset = target.len();
+                    let content = source
+                        .get(offset..offset.saturating_add(length))
+                        .ok_or(InstructionError::InvalidContent)?;
+                    target.extend_from_slice(content);
+                    builder.copy_from(source, offset, &vec![0; length]);
+                }
+                BPS_TARGET_READ => {
+                    let content = read_bytes(&mut body, length)?;
+                    target.extend_from_slice(content);
+                    builder.add(content);
+                }
+                BPS_SOURCE_COPY => {
+                    let relative = read_number(&mut body)?;
+                    source_offset = source_offset
+                        .checked_add_signed(relative_offset(relative))
+                        .filter(|offset| {
+                            offset
+                                .checked_add(length)
+                                .is_some_and(|end| end <= source.len())
+                        })
+                        .ok_or(InstructionError::InvalidContent)?;
+                    target.extend_from_slice(&source[source_offset..source_offset + length]);
+                    builder.copy_from(source, source_offset, &vec![0; length]);
+                    source_offset += length;
+                }
+                BPS_TARGET_COPY => {
+                    let relative = read_number(&mut body)?;
+                    target_offset = target_offset
+                        .checked_add_signed(relative_offset(relative))
+                        .filter(|offset| *offset < target.len())
+                        .ok_or(InstructionError::InvalidContent)?;
+                    let start = target.len();
+                    for index in target_offset..target_offset + length {
+                        target.push(target[index]);
+                    }
+                    builder.add(&target[start..]);
+                    target_offset += length;
+                }
+                _ => unreachable!(),
+            }
+        }
+        if target.len() != target_length {
+            return Err(InstructionError::MissingContent);
+        }
+        Ok(Self {
+            patch: builder.finish(source.len()),
+            source_checksum: Some(source_checksum),
+            target_checksum: Some(target_checksum),
+        })
+    }
+}
+
+fn relative_offset(value: usize) -> isize {
+    let magnitude = (value >> 1) as isize;
+    if value & 1 != 0 {
+        -magnitude
+    } else {
+        magnitude
+    }
+}
+
+impl Patch {
+    pub fn to_ips(&self, source: &[u8]) -> Option<Vec<u8>> {
+        let target = self.apply(source)?;
+        if target.len() > IPS_MAX_OFFSET + 1 {
+            return None;
+        }
+        let changed = |index: usize| source.get(index) != target.get(index);
+        let mut bytes: Vec<u8> = IPS_MAGIC.to_vec();
+        let mut start = 0;
+        while start < target.len() {
+            if !changed(start) {
+                start += 1;
+                continue;
+            }
+            if start == 0x454F46 {
+                start -= 1;
+            }
+            let mut end = start + 1;
+            while end < target.len()
+                && end - start < IPS_MAX_RECORD_LENGTH
+                && (start..(end + IPS_RECORD_GAP).min(target.len()))
+                    .skip(end - start)
+                    .any(changed)
+            {
+                end += 1;
+            }
+            bytes.extend(&(start as u32).to_be_bytes()[1..]);
+            bytes.extend(((end - start) as u16).to_be_bytes());
+            bytes.extend_from_slice(&target[start..end]);
+            start = end;
+        }
+        bytes.extend_from_slice(IPS_FOOTER);
+        if target.len() < source.len() {
+            bytes.extend(&(target.len() as u32).to_be_bytes()[1..]);
+        }
+        Some(bytes)
+    }
+
+    pub fn to_ups(&self, source: &[u8]) -> Option<Vec<u8>> {
+        let target = self.apply(source)?;
+        let mut bytes: Vec<u8> = UPS_MAGIC.to_vec();
+        write_number(&mut bytes, source.len());
+        write_number(&mut bytes, target.len());
+        let xor = |index: usize| {
+            source.get(index).copied().unwrap_or_default()
+                ^ target.get(index).copied().unwrap_or_default()
+        };
+        let mut position = 0;
+        let mut index = 0;
+        while index < source.len().max(target.len()) {
+            if xor(index) == 0 {
+                index += 1;
+                continue;
+            }
+            write_number(&mut bytes, index - position);
+            while index < source.len().max(target.len()) && xor(index) != 0 {
+                bytes.push(xor(index));
+                index += 1;
+            }
+            bytes.push(0);
+            index += 1;
+            position = index;
+        }
+        push_footer(&mut bytes, source, &target);
+        Some(bytes)
+    }
+
+    pub fn to_bps(&self, source: &[u8]) -> Option<Vec<u8>> {
+        let operations = self.operations(source, 1)?;
+        let target = self.apply(source)?;
+        let mut bytes: Vec<u8> = BPS_MAGIC.to_vec();
+        write_number(&mut bytes, source.len());
+        write_number(&mut bytes, target.len());
+        write_number(&mut bytes, 0);
+        let mut written = 0usize;
+        let mut source_offset = 0usize;
+        for operation in operations.iter() {
+            let length = operation.len();
+            match operation {
+                Operation::Add(content) => {
+                    write_number(&mut bytes, ((length - 1) << 2) | BPS_TARGET_READ);
+                    bytes.extend_from_slice(content);
+                }
+                Operation::Copy { offset, .. } if *offset == written => {
+                    write_number(&mut bytes, ((length - 1) << 2) | BPS_SOURCE_READ);
+                }
+                Operation::Copy { offset, .. } => {
+                    write_number(&mut bytes, ((length - 1) << 2) | BPS_SOURCE_COPY);
+                    let relative = offset.abs_diff(source_offset) << 1;
+                    write_number(&mut bytes, relative | (*offset < source_offset) as usize);
+                    source_offset = offset + length;
+                }
+            }
+            written += length;
+        }
+        push_footer(&mut bytes, source, &target);
+        Some(bytes)
+    }
+}
+
+#[cfg(test)]
+mod rom_tests {
+    use std::fs;
+
+    use super::*;
+
+    fn files() -> (Vec<u8>, Vec<u8>) {
+        (
+            fs::read("files/source.txt").unwrap(),
+            fs::read("files/target.txt").unwrap(),
+        )
+    }
+
+    #[test]
+    fn number() {
+        for value in [0, 1, 127, 128, 255, 16511, 16512, 1 << 40] {
+            let mut bytes = Vec::new();
+            write_number(&mut bytes, value);
+            assert_eq!(read_number(&mut bytes.iter()), Ok(value));
+        }
+        let mut bytes = Vec::new();
+        write_number(&mut bytes, 128);
+        assert_eq!(bytes, [0x00, 0x80]);
+        assert_eq!(
+            read_number(&mut [0x7F; 12].iter()),
+            Err(InstructionError::InvalidLength)
+        );
+    }
+
+    #[test]
+    fn ips() {
+        let (source, target) = files();
+        let bytes = Patch::new(&source, &target).to_ips(&source).unwrap();
+        let patch = RomPatch::from_ips(&bytes, &source).unwrap();
+        assert_eq!(patch.source_checksum(), None);
+        assert_eq!(patch.apply(&source).unwrap(), target);
+
+        let bytes = Patch::new(b"ABCDEF", b"ABXD").to_ips(b"ABCDEF").unwrap();
+        assert_eq!(bytes, b"PATCH\0\0\x02\0\x01XEOF\0\0\x04");
+    }
+
+    #[test]
+    fn ips_run() {
+        let bytes = b"PATCH\0\0\x01\0\0\0\x04ZEOF";
+        let patch = RomPatch::from_ips(bytes, b"AB").unwrap();
+        assert_eq!(patch.apply(b"AB").unwrap(), b"AZZZZ");
+    }
+
+    #[test]
+    fn ups() {
+        let (source, target) = files();
+        let bytes = Patch::new(&source, &target).to_ups(&source).unwrap();
+        let patch = RomPatch::from_ups(&bytes, &source).unwrap();
+        assert_eq!(patch.source_checksum(), Some(crc32(&source)));
+        assert_eq!(patch.target_checksum(), Some(crc32(&target)));
+        assert_eq!(patch.apply(&source).unwrap(), target);
+
+        let bytes = Patch::new(b"ABCD", b"AXCDE").to_ups(b"ABCD").unwrap();
+        assert_eq!(
+            &bytes[4..11],
+            [0x84, 0x85, 0x81, b'X' ^ b'B', 0, 0x81, b'E']
+        );
+    }
+
+    #[test]
+    fn bps() {
+        let (source, target) = files();
+        let bytes = Patch::new(&source, &target).to_bps(&source).unwrap();
+        let patch = RomPatch::from_bps(&bytes, &source).unwrap();
+        assert_eq!(patch.apply(&source).unwrap(), target);
+    }
+
+    #[test]
+    fn crafted_lengths() {
+        let mut bytes = UPS_MAGIC.to_vec();
+        write_number(&mut bytes, 4);
+        write_number(&mut bytes, usize::MAX);
+        push_footer(&mut bytes, b"ABCD", b"");
+        assert_eq!(
+            RomPatch::from_ups(&bytes, b"ABCD"),
+            Err(InstructionError::InvalidLength)
+        );
+
+        for (action, relative) in [
+            ((usize::MAX >> 3 << 2) | BPS_SOURCE_READ, None),
+            (BPS_SOURCE_COPY, Some(usize::MAX - 1)),
+        ] {
+            let mut bytes = BPS_MAGIC.to_vec();
+            write_number(&mut bytes, 4);
+            write_number(&mut bytes, MAX_TARGET_LENGTH);
+            write_number(&mut bytes, 0);
+            write_number(&mut bytes, action);
+            if let Some(relative) = relative {
+                write_number(&mut bytes, relative);
+            }
+            push_footer(&mut bytes, b"ABCD", b"");
+            assert_eq!(
+                RomPatch::from_bps(&bytes, b"ABCD"),
+                Err(InstructionError::InvalidContent)
+            );
+        }
+
+        let mut bytes = UPS_MAGIC.to_vec();
+        write_number(&mut bytes, 4);
+        write_number(&mut bytes, MAX_TARGET_LENGTH + 1);
+        write_number(&mut bytes, MAX_TARGET_LENGTH);
+        bytes.push(0);
+        push_footer(&mut bytes, b"ABCD", b"");
+        assert_eq!(
+            RomPatch::from_ups(&bytes, b"ABCD"),
+            Err(InstructionError::InvalidLength)
+        );
+
+        let mut bytes = BPS_MAGIC.to_vec();
+        write_number(&mut bytes, 4);
+        write_number(&mut bytes, MAX_TARGET_LENGTH + 1);
+        write_number(&mut bytes, 0);
+        push_footer(&mut bytes, b"ABCD", b"");
+        assert_eq!(
+            RomPatch::from_bps(&bytes, b"ABCD"),
+            Err(InstructionError::InvalidLength)
+        );
+    }
+
+    #[test]
+    fn bps_copies() {
+        let mut bytes = BPS_MAGIC.to_vec();
+        write_number(&mut bytes, 4);
+        write_number(&mut bytes, 10);
+        write_number(&mut bytes, 0);
+        write_number(&mut bytes, (1 << 2) | BPS_SOURCE_READ);
+        write_number(&mut bytes, BPS_TARGET_READ);
+        bytes.push(b'-');
+        write_number(&mut bytes, (1 << 2) | BPS_SOURCE_COPY);
+        write_number(&mut bytes, 2 << 1);
+        write_number(&mut bytes, (4 << 2) | BPS_TARGET_COPY);
+        write_number(&mut bytes, 3 << 1);
+        push_footer(&mut bytes, b"ABCD", b"AB-CDCDCDC");
+
+        let patch = RomPatch::from_bps(&bytes, b"ABCD").unwrap();
+        assert_eq!(patch.apply(b"ABCD").unwrap(), b"AB-CDCDCDC");
+        let exported = patch.patch().to_bps(b"ABCD").unwrap();
+        assert_eq!(
+            RomPatch::from_bps(&exported, b"ABCD")
+                .unwrap()
+                .apply(b"ABCD")
+                .unwrap(),
+            b"AB-CDCDCDC"
+        );
+    }
+
+    #[test]
+    fn checksums() {
+        let (source, target) = files();
+        let mut bytes = Patch::new(&source, &target).to_bps(&source).unwrap();
+        let length = bytes.len();
+        bytes[length - 1] ^= 1;
+        assert_eq!(
+            RomPatch::from_bps(&bytes, &source),
+            Err(InstructionError::InvalidChecksum)
+        );
+        assert_eq!(
+            RomPatch::from_bps(&bytes[..length - 1], b"ABCD"),
+            Err(InstructionError::InvalidChecksum)
+        );
+
+        let patch = RomPatch::new(Patch::new(b"ABCD", b"ABXD"), b"ABCD").unwrap();
+        assert_eq!(patch.apply(b"ABCE"), Err(InstructionError::SourceMismatch));
+        let mut tampered = patch.clone();
+        tampered.target_checksum = Some(0);
+        assert_eq!(
+            tampered.apply(b"ABCD"),
+            Err(InstructionError::InvalidChecksum)
+        );
+    }
+}
@@ -0,0 +1,232 @@
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use crate::{
+    instructions::{delta_instruction::DeltaInstruction, InstructionInfo},
+    patch::Patch,
+};
+
+const START: SeekEntry = SeekEntry {
+    target_offset: 0,
+    instruction: 0,
+    source_offset: 0,
+};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct SeekEntry {
+    pub target_offset: usize,
+    pub instruction: usize,
+    pub source_offset: usize,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SeekIndex {
+    entries: Vec<SeekEntry>,
+    source_length: usize,
+    target_length: usize,
+}
+
+impl SeekIndex {
+    pub fn new(patch: &Patch, interval: usize) -> Self {
+        let interval = interval.max(1);
+        let mut entries: Vec<SeekEntry> = Vec::new();
+        let (mut target_offset, mut source_offset) = (0, 0);
+        for (index, instruction) in patch.instructions().iter().enumerate() {
+            if entries
+                .last()
+                .is_none_or(|entry| target_offset - entry.target_offset >= interval)
+            {
+                entries.push(SeekEntry {
+                    target_offset,
+                    instruction: index,
+                    source_offset,
+                });
+            }
+            let length = instruction.len() as usize;
+            match instruction {
+                DeltaInstruction::Remove(_) => source_offset += length,
+                DeltaInstruction::Add(_) | DeltaInstruction::OffsetCopy(_) => {
+                    target_offset += length
+                }
+                DeltaInstruction::Copy(_) => {
+                    source_offset += length;
+                    target_offset += length;
+                }
+            }
+        }
+        Self {
+            entries,
+            source_length: source_offset,
+            target_length: target_offset,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn source_length(&self) -> usize {
+        self.source_length
+    }
+
+    pub fn target_length(&self) -> usize {
+        self.target_length
+    }
+
+    pub fn seek(&self, target_offset: usize) -> Option<SeekEntry> {
+        let index = self
+            .entries
+            .partition_point(|entry| entry.target_offset <= target_offset);
+        index.checked_sub(1).map(|index| self.entries[index])
+    }
+}
+
+fn read_range(
+    instructions: &[DeltaInstruction],
+    source: &[u8],
+    start: SeekEntry,
+    range: Range<usize>,
+) -> Option<Vec<u8>> {
+    let mut target: Vec<u8> = Vec::with_capacity(range.len());
+    let mut target_offset = start.target_offset;
+    let mut position = start.source_offset;
+    for instruction in instructions[start.instruction..].iter() {
+        if target_offset >= range.end {
+            break;
+        }
+        let length = instruction.len() as usize;
+        let skip = range.start.saturating_sub(target_offset).min(length);
+        let take = range.end.min(target_offset + length) - target_offset;
+        let take = take.saturating_sub(skip);
+        match instruction {
+            DeltaInstruction::Remove(_) => {
+                position += length;
+                continue;
+            }
+            DeltaInstruction::Add(instruction) => {
+                target.extend_from_slice(&instruction.content()[skip..skip + take])
+            }
+            DeltaInstruction::Copy(instruction) => {
+                let source = source.get(position + skip..position + skip + take)?;
+                let difference = &instruction.content()[skip..skip + take];
+                target.extend(
+                    source
+                        .iter()
+                        .zip(difference)
+                        .map(|(source_item, item)| source_item.wrapping_add(*item)),
+                );
+                position += length;
+            }
+            DeltaInstruction::OffsetCopy(instruction) => {
+                let offset = usize::try_from(instruction.offset())
+                    .ok()?
+                    .checked_add(skip)?;
+                target.extend_from_slice(source.get(offset..offset.checked_add(take)?)?);
+            }
+        }
+        target_offset += length;
+    }
+    (target.len() == range.len()).then_some(target)
+}
+
+impl Patch {
+    pub fn apply_range(&self, source: &[u8], range: Range<usize>) -> Option<Vec<u8>> {
+        let start = match self.seek_index() {
+            Some(index) => {
+                if source.len() != index.source_length() || range.end > index.target_length() {
+                    return None;
+                }
+                index.seek(range.start).unwrap_or(START)
+            }
+            None => {
+                if source.len() != self.source_lenth() || range.end > self.target_length() {
+                    return None;
+                }
+                START
+            }
+        };
+        if range.start > range.end {
+            return None;
+        }
+        read_range(self.instructions(), source, start, range)
+    }
+}
+
+#[cfg(test)]
+mod seek_tests {
+    use std::fs;
+
+    use crate::instructions::{
+        copy_instruction::CopyInstruction, offset_copy_instruction::OffsetCopyInstruction,
+    };
+
+    use super::*;
+
+    #[test]
+    fn seek_index() {
+        let source = fs::read("files/source.txt").unwrap();
+        let target = fs::read("files/target.txt").unwrap();
+        let patch = Patch::new(&source, &target);
+        let index = SeekIndex::new(&patch, 512);
+        assert_eq!(index.source_length(), source.len());
+        assert_eq!(index.target_length(), target.len());
+        assert!(index.len() > 2 && index.len() <= target.len() / 512 + 1);
+        assert_eq!(index.seek(0), Some(START));
+        for offset in [0, 700, 1_800, target.len() - 1] {
+            let entry = index.seek(offset).unwrap();
+            assert!(entry.target_offset <= offset && offset - entry.target_offset < 512 + 255);
+            let prefix =
+                Patch::from_instructions(patch.instructions()[..entry.instruction].to_vec());
+            assert_eq!(prefix.target_length(), entry.target_offset);
+            assert_eq!(prefix.source_lenth(), entry.source_offset);
+        }
+        assert!(SeekIndex::new(&Patch::default(), 0).is_empty());
+        assert_eq!(
+            Patch::default().with_seek_index(16).apply_range(b"", 0..0),
+            Some(Vec::new())
+        );
+    }
+
+    #[test]
+    fn apply_range() {
+        let source = fs::read("files/source.txt").unwrap();
+        let target = fs::read("files/target.txt").unwrap();
+        let patch = Patch::new(&source, &target);
+        let indexed = patch.clone().with_seek_index(256);
+        assert_eq!(indexed, patch);
+        assert!(patch.seek_index().is_none());
+        for range in [0..0, 0..10, 300..1_300, 1_000..1_001, 2_000..target.len()] {
+            assert_eq!(
+                patch.apply_range(&source, range.clone()).as_deref(),
+                Some(&target[range.clone()])
+            );
+            assert_eq!(
+                indexed.apply_range(&source, range.clone()).as_deref(),
+                Some(&target[range])
+            );
+        }
+        assert_eq!(indexed.apply_range(&source, 0..target.len() + 1), None);
+        assert_eq!(indexed.apply_range(&source[1..], 0..10), None);
+        assert_eq!(patch.apply_range(&source[1..], 0..10), None);
+
+        let patch = Patch::from_instructions(vec![
+            OffsetCopyInstruction::new(1, 2).into(),
+            CopyInstruction::new(vec![0, 1, 0]).into(),
+        ])
+        .with_seek_index(1);
+        assert_eq!(patch.apply_range(b"ABC", 1..4), Some(b"CAC".to_vec()));
+        assert_eq!(patch.apply_range(b"ABC", 3..5), Some(b"CC".to_vec()));
+
+        let patch = Patch::from_instructions(vec![
+            CopyInstruction::new(vec![0, 0, 0]).into(),
+            OffsetCopyInstruction::new(u64::MAX - 1, 1).into(),
+        ]);
+        assert_eq!(patch.apply_range(b"ABC", 1..3), Some(b"BC".to_vec()));
+        assert_eq!(patch.apply_range(b"ABC", 2..4), None);
+        assert_eq!(patch.with_seek_index(1).apply_range(b"ABC", 3..4), None);
+    }
+}
@@ -0,0 +1,329 @@
+use alloc::vec::Vec;
+use core::fmt;
+
+use serde::{
+    de::{self, SeqAccess, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use crate::{
+    instructions::{
+        add_instruction::AddInstruction, copy_instruction::CopyInstruction,
+        delta_instruction::DeltaInstruction, offset_copy_instruction::OffsetCopyInstruction,
+        remove_instruction::RemoveInstruction, InstructionBytes, InstructionError, InstructionInfo,
+        Result,
+    },
+    patch::Patch,
+};
+
+struct BytesVisitor;
+
+impl<'de> Visitor<'de> for BytesVisitor {
+    type Value = Vec<u8>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "encoded patch bytes")
+    }
+
+    fn visit_bytes<E: de::Error>(self, value: &[u8]) -> core::result::Result<Self::Value, E> {
+        Ok(value.to_vec())
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, value: Vec<u8>) -> core::result::Result<Self::Value, E> {
+        Ok(value)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(
+        self,
+        mut seq: A,
+    ) -> core::result::Result<Self::Value, A::Error> {
+        let mut bytes: Vec<u8> = Vec::with_capacity(seq.size_hint().unwrap_or_default());
+        while let Some(item) = seq.next_element()? {
+            bytes.push(item);
+        }
+        Ok(bytes)
+    }
+}
+
+fn deserialize_bytes<'de, D, T>(
+    deserializer: D,
+    parse: impl FnOnce(&[u8]) -> Result<T>,
+) -> core::result::Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let bytes = deserializer.deserialize_byte_buf(BytesVisitor)?;
+    parse(&bytes).map_err(de::Error::custom)
+}
+
+fn deserialize_instruction<'de, D, T>(deserializer: D) -> core::result::Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: InstructionBytes,
+{
+    deserialize_bytes(deserializer, |bytes| {
+        let mut bytes_iter = bytes.iter().peekable();
+        let instruction = T::try_from_bytes(&mut bytes_iter)?;
+        match bytes_iter.peek() {
+            Some(_) => Err(InstructionError::InvalidContent),
+            None => Ok(instruction),
+        }
+    })
+}
+
+fn deserialize_content<'de, D>(deserializer: D) -> core::result::Result<Vec<u8>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let content = Vec::<u8>::deserialize(deserializer)?;
+    if content.len() > u8::MAX as usize {
+        return Err(de::Error::custom(InstructionError::ContentOverflow));
+    }
+    Ok(content)
+}
+
+impl Serialize for RemoveInstruction {
+    fn serialize<S: Serializer>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_u8(self.len())
+        } else {
+            serializer.serialize_bytes(&self.to_bytes())
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for RemoveInstruction {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> core::result::Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            Ok(RemoveInstruction::new(u8::deserialize(deserializer)?))
+        } else {
+            deserialize_instruction(deserializer)
+        }
+    }
+}
+
+impl Serialize for AddInstruction {
+    fn serialize<S: Serializer>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            self.content().serialize(serializer)
+        } else {
+            serializer.serialize_bytes(&self.to_bytes())
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for AddInstruction {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> core::result::Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            Ok(AddInstruction::new(deserialize_content(deserializer)?))
+        } else {
+            deserialize_instruction(deserializer)
+        }
+    }
+}
+
+impl Serialize for CopyInstruction {
+    fn serialize<S: Serializer>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            self.content().serialize(serializer)
+        } else {
+            serializer.serialize_bytes(&self.to_bytes())
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for CopyInstruction {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> core::result::Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            Ok(CopyInstruction::new(deserialize_content(deserializer)?))
+        } else {
+            deserialize_instruction(deserializer)
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "OffsetCopyInstruction")]
+struct ReadableOffsetCopy {
+    offset: u64,
+    length: u8,
+}
+
+impl Serialize for OffsetCopyInstruction {
+    fn serialize<S: Serializer>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            ReadableOffsetCopy {
+                offset: self.offset(),
+                length: self.len(),
+            }
+            .serialize(serializer)
+        } else {
+            serializer.serialize_bytes(&self.to_bytes())
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for OffsetCopyInstruction {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> core::result::Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            let readable = ReadableOffsetCopy::deserialize(deserializer)?;
+            if readable
+                .offset
+                .checked_add(readable.length as u64)
+                .is_none()
+            {
+                return Err(de::Error::custom(InstructionError::InvalidContent));
+            }
+            Ok(OffsetCopyInstruction::new(readable.offset, readable.length))
+        } else {
+            deserialize_instruction(deserializer)
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "DeltaInstruction", rename_all = "snake_case")]
+enum ReadableInstruction {
+    Remove(RemoveInstruction),
+    Add(AddInstruction),
+    Copy(CopyInstruction),
+    OffsetCopy(OffsetCopyInstruction),
+}
+
+impl From<ReadableInstruction> for DeltaInstruction {
+    fn from(value: ReadableInstruction) -> Self {
+        match value {
+            ReadableInstruction::Remove(instruction) => instruction.into(),
+            ReadableInstruction::Add(instruction) => instruction.into(),
+            ReadableInstruction::Copy(instruction) => instruction.into(),
+            ReadableInstruction::OffsetCopy(instruction) => instruction.into(),
+        }
+    }
+}
+
+impl Serialize for DeltaInstruction {
+    fn serialize<S: Serializer>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error> {
+        if !serializer.is_human_readable() {
+            return serializer.serialize_bytes(&self.to_bytes());
+        }
+        match self {
+            DeltaInstruction::Remove(instruction) => {
+                serializer.serialize_newtype_variant("DeltaInstruction", 0, "remove", instruction)
+            }
+            DeltaInstruction::Add(instruction) => {
+                serializer.serialize_newtype_variant("DeltaInstruction", 1, "add", instruction)
+            }
+            DeltaInstruction::Copy(instruction) => {
+                serializer.serialize_newtype_variant("DeltaInstruction", 2, "copy", instruction)
+            }
+            DeltaInstruction::OffsetCopy(instruction) => serializer.serialize_newtype_variant(
+                "DeltaInstruction",
+                3,
+                "offset_copy",
+                instruction,
+            ),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for DeltaInstruction {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> core::result::Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            Ok(ReadableInstruction::deserialize(deserializer)?.into())
+        } else {
+            deserialize_instruction(deserializer)
+        }
+    }
+}
+
+impl Serialize for Patch {
+    fn serialize<S: Serializer>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.collect_seq(self.instructions())
+        } else {
+            serializer.serialize_bytes(&self.to_bytes())
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Patch {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> core::result::Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            Ok(Patch::from_instructions(Vec::deserialize(deserializer)?))
+        } else {
+            deserialize_bytes(deserializer, Patch::try_from_bytes)
+        }
+    }
+}
+
+#[cfg(test)]
+mod serialization_tests {
+    use std::fs;
+
+    use super::*;
+
+    fn patch() -> Patch {
+        Patch::from_instructions(vec![
+            RemoveInstruction::new(2).into(),
+            AddInstruction::new(b"AB".to_vec()).into(),
+            CopyInstruction::new(vec![0, 3]).into(),
+            OffsetCopyInstruction::new(1, 4).into(),
+        ])
+    }
+
+    #[test]
+    fn json() {
+        let json = serde_json::to_string(&patch()).unwrap();
+        assert_eq!(
+            json,
+            r#"[{"remove":2},{"add":[65,66]},{"copy":[0,3]},{"offset_copy":{"offset":1,"length":4}}]"#
+        );
+        assert_eq!(serde_json::from_str::<Patch>(&json).unwrap(), patch());
+    }
+
+    #[test]
+    fn json_err() {
+        assert!(serde_json::from_str::<Patch>(r#"[{"move":2}]"#).is_err());
+        assert!(serde_json::from_str::<AddInstruction>(&format!("{:?}", [0u8; 256])).is_err());
+        assert!(serde_json::from_str::<OffsetCopyInstruction>(&format!(
+            r#"{{"offset":{},"length":1}}"#,
+            u64::MAX
+        ))
+        .is_err());
+    }
+
+    #[test]
+    fn bincode() {
+        let bytes = bincode::serialize(&patch()).unwrap();
+        let patch_bytes = patch().to_bytes();
+        assert_eq!(bytes[..8], (patch_bytes.len() as u64).to_le_bytes());
+        assert_eq!(bytes[8..], patch_bytes);
+        assert_eq!(bincode::deserialize::<Patch>(&bytes).unwrap(), patch());
+
+        let instruction: DeltaInstruction = AddInstruction::new(b"AB".to_vec()).into();
+        let bytes = bincode::serialize(&instruction).unwrap();
+        assert_eq!(
+            bincode::deserialize::<DeltaInstruction>(&bytes).unwrap(),
+            instruction
+        );
+    }
+
+    #[test]
+    fn bincode_err() {
+        let bytes = bincode::serialize(&vec![b'?', 1u8]).unwrap();
+        assert!(bincode::deserialize::<Patch>(&bytes).is_err());
+        let bytes = bincode::serialize(&vec![b'-', 1u8, b'-', 1u8]).unwrap();
+        assert!(bincode::deserialize::<RemoveInstruction>(&bytes).is_err());
+    }
+
+    #[test]
+    fn round_trip() {
+        let source = fs::read("files/source.txt").unwrap();
+        let target = fs::read("files/target.txt").unwrap();
+        let patch = Patch::new(&source, &target);
+        let json = serde_json::to_vec(&patch).unwrap();
+        assert_eq!(serde_json::from_slice::<Patch>(&json).unwrap(), patch);
+        let bytes = bincode::serialize(&patch).unwrap();
+        assert_eq!(bincode::deserialize::<Patch>(&bytes).unwrap(), patch);
+    }
+}
@@ -0,0 +1,96 @@
+use alloc::vec::Vec;
+
+use crate::chunking::{chunk_hash, mix, Chunker};
+
+const SIGNATURE_LENGTH: usize = 64;
+const CHUNKER: Chunker = Chunker::new(16, 64, 256);
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Signature {
+    minimums: [u64; SIGNATURE_LENGTH],
+}
+
+impl Signature {
+    pub fn new(bytes: &[u8]) -> Self {
+        let mut minimums = [u64::MAX; SIGNATURE_LENGTH];
+        for chunk in CHUNKER.split(bytes) {
+            let hash = chunk_hash(chunk);
+            for (seed, minimum) in minimums.iter_mut().enumerate() {
+                *minimum = (*minimum).min(mix(hash ^ mix(seed as u64)));
+            }
+        }
+        Self { minimums }
+    }
+
+    pub fn similarity(&self, other: &Signature) -> f64 {
+        let equal = self
+            .minimums
+            .iter()
+            .zip(other.minimums.iter())
+            .filter(|(minimum, other)| minimum == other)
+            .count();
+        equal as f64 / SIGNATURE_LENGTH as f64
+    }
+}
+
+pub fn rank_bases<S: AsRef<[u8]>>(candidates: &[S], target: &[u8]) -> Vec<(usize, f64)> {
+    let target = Signature::new(target);
+    let mut ranking: Vec<(usize, f64)> = candidates
+        .iter()
+        .enumerate()
+        .map(|(index, candidate)| {
+            (
+                index,
+                Signature::new(candidate.as_ref()).similarity(&target),
+            )
+        })
+        .collect();
+    ranking.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+    ranking
+}
+
+pub fn choose_base<S: AsRef<[u8]>>(candidates: &[S], target: &[u8]) -> Option<usize> {
+    rank_bases(candidates, target)
+        .first()
+        .map(|(index, _)| *index)
+}
+
+#[cfg(test)]
+mod similarity_tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn signature() {
+        let source = fs::read("files/source.txt").unwrap();
+        let target = fs::read("files/target.txt").unwrap();
+        let signature = Signature::new(&source);
+        assert_eq!(signature.similarity(&Signature::new(&source)), 1.0);
+
+        let mut edited = source.clone();
+        edited[100] = b'#';
+        edited.extend_from_slice(b"appended line\n");
+        let edited = Signature::new(&edited);
+        let unrelated = Signature::new(&target.iter().rev().copied().collect::<Vec<u8>>());
+        assert!(signature.similarity(&edited) > 0.5);
+        assert!(signature.similarity(&edited) > signature.similarity(&unrelated));
+        assert_eq!(Signature::new(b"").similarity(&Signature::new(b"")), 1.0);
+    }
+
+    #[test]
+    fn choose() {
+        let source = fs::read("files/source.txt").unwrap();
+        let target = fs::read("files/target.txt").unwrap();
+        let mut newer = source.clone();
+        newer.splice(200..200, b"new paragraph\n".iter().copied());
+        let candidates = [target.clone(), source.clone(), Vec::new()];
+
+        assert_eq!(choose_base(&candidates, &newer), Some(1));
+        let ranking = rank_bases(&candidates, &newer);
+        assert_eq!(ranking[0].0, 1);
+        assert!(ranking[0].1 > ranking[1].1);
+        assert_eq!(ranking.len(), 3);
+        assert_eq!(choose_base::<&[u8]>(&[], &newer), None);
+    }
+}
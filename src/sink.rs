@@ -0,0 +1,191 @@
+use alloc::vec::Vec;
+
+use crate::{
+    instructions::{InstructionError, Result},
+    patch::Patch,
+    view::InstructionView,
+};
+
+pub trait Sink {
+    fn write(&mut self, bytes: &[u8]) -> Result<()>;
+}
+
+impl Sink for Vec<u8> {
+    fn write(&mut self, bytes: &[u8]) -> Result<()> {
+        self.extend_from_slice(bytes);
+        Ok(())
+    }
+}
+
+impl Sink for &mut [u8] {
+    fn write(&mut self, bytes: &[u8]) -> Result<()> {
+        if self.len() < bytes.len() {
+            return Err(InstructionError::OutputTooSmall {
+                available: self.len(),
+                required: bytes.len(),
+            });
+        }
+        let (head, tail) = core::mem::take(self).split_at_mut(bytes.len());
+        head.copy_from_slice(bytes);
+        *self = tail;
+        Ok(())
+    }
+}
+
+pub(crate) fn write_instruction<S: Sink + ?Sized>(
+    instruction: InstructionView<'_>,
+    source: &[u8],
+    position: &mut usize,
+    sink: &mut S,
+) -> Result<()> {
+    match instruction {
+        InstructionView::Remove(length) => *position += length as usize,
+        InstructionView::Add(content) => sink.write(content)?,
+        InstructionView::Copy(content) => {
+            let mut buffer = [0u8; u8::MAX as usize];
+            for ((item, source_item), difference) in
+                buffer.iter_mut().zip(&source[*position..]).zip(content)
+            {
+                *item = source_item.wrapping_add(*difference);
+            }
+            sink.write(&buffer[..content.len()])?;
+            *position += content.len();
+        }
+        InstructionView::OffsetCopy { offset, length } => {
+            let content = usize::try_from(offset)
+                .ok()
+                .and_then(|offset| source.get(offset..offset.checked_add(length as usize)?))
+                .ok_or(InstructionError::SourceMismatch)?;
+            sink.write(content)?;
+        }
+    }
+    Ok(())
+}
+
+impl Patch {
+    pub fn apply_to<S: Sink + ?Sized>(&self, source: &[u8], sink: &mut S) -> Result<()> {
+        if source.len() != self.source_lenth() || !self.offsets_fit(source.len()) {
+            return Err(InstructionError::SourceMismatch);
+        }
+        let mut position = 0usize;
+        for instruction in self.instructions() {
+            write_instruction(instruction.into(), source, &mut position, sink)?;
+        }
+        Ok(())
+    }
+
+    pub fn apply_into(&self, source: &[u8], out: &mut [u8]) -> Result<usize> {
+        let target_length = self.target_length();
+        if out.len() < target_length {
+            return Err(InstructionError::OutputTooSmall {
+                available: out.len(),
+                required: target_length,
+            });
+        }
+        self.apply_to(source, &mut &mut out[..])?;
+        Ok(target_length)
+    }
+}
+
+#[cfg(test)]
+mod sink_tests {
+    use std::fs;
+
+    use super::*;
+
+    struct Checksum {
+        sum: u32,
+        length: usize,
+    }
+
+    impl Sink for Checksum {
+        fn write(&mut self, bytes: &[u8]) -> Result<()> {
+            self.sum = bytes
+                .iter()
+                .fold(self.sum, |sum, item| sum.wrapping_add(*item as u32));
+            self.length += bytes.len();
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn apply_to() {
+        let source = fs::read("files/source.txt").unwrap();
+        let target = fs::read("files/target.txt").unwrap();
+        let patch = Patch::new(&source, &target);
+
+        let mut output: Vec<u8> = Vec::new();
+        patch.apply_to(&source, &mut output).unwrap();
+        assert_eq!(output, target);
+
+        let mut checksum = Checksum { sum: 0, length: 0 };
+        patch.apply_to(&source, &mut checksum).unwrap();
+        assert_eq!(checksum.length, target.len());
+        assert_eq!(
+            checksum.sum,
+            target.iter().map(|item| *item as u32).sum::<u32>()
+        );
+
+        assert_eq!(
+            patch.apply_to(&target, &mut output),
+            Err(InstructionError::SourceMismatch)
+        );
+    }
+
+    #[test]
+    fn apply_into() {
+        let source = fs::read("files/source.txt").unwrap();
+        let target = fs::read("files/target.txt").unwrap();
+        let patch = Patch::new(&source, &target);
+
+        let mut out = vec![0u8; target.len() + 3];
+        assert_eq!(patch.apply_into(&source, &mut out), Ok(target.len()));
+        assert_eq!(&out[..target.len()], target);
+        assert_eq!(out[target.len()..], [0; 3]);
+
+        assert_eq!(
+            patch.apply_into(&source, &mut out[..target.len() - 1]),
+            Err(InstructionError::OutputTooSmall {
+                available: target.len() - 1,
+                required: target.len()
+            })
+        );
+        assert_eq!(
+            patch.apply_into(&target, &mut out),
+            Err(InstructionError::SourceMismatch)
+        );
+    }
+
+    #[test]
+    fn slice_sink() {
+        let mut out = [0u8; 4];
+        let mut sink = &mut out[..];
+        sink.write(b"AB").unwrap();
+        sink.write(b"C").unwrap();
+        assert_eq!(
+            sink.write(b"DE"),
+            Err(InstructionError::OutputTooSmall {
+                available: 1,
+                required: 2
+            })
+        );
+        assert_eq!(&out, b"ABC\0");
+    }
+
+    #[test]
+    fn offset_copy_out_of_range() {
+        let mut output: Vec<u8> = Vec::new();
+        for (offset, length) in [(u64::MAX, 3), (2, 2)] {
+            assert_eq!(
+                write_instruction(
+                    InstructionView::OffsetCopy { offset, length },
+                    b"ABC",
+                    &mut 0,
+                    &mut output
+                ),
+                Err(InstructionError::SourceMismatch)
+            );
+        }
+        assert!(output.is_empty());
+    }
+}
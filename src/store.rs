@@ -0,0 +1,347 @@
+use alloc::vec::Vec;
+
+use crate::{
+    container::{read_field, Compression, MAX_DECOMPRESSED_LENGTH},
+    instructions::{
+        delta_instruction::DeltaInstruction, InstructionError, InstructionInfo, Result,
+    },
+    patch::Patch,
+};
+
+const STORE_MAGIC: &[u8; 4] = b"DLTV";
+const STORE_VERSION: u8 = 1;
+const KEYFRAME_SIGN: u8 = b'K';
+const DELTA_SIGN: u8 = b'D';
+const DEFAULT_MAX_DEPTH: usize = 16;
+
+#[derive(Debug, PartialEq, Clone)]
+enum Version {
+    Keyframe(Vec<u8>),
+    Delta(Patch),
+}
+
+impl Version {
+    fn to_bytes(&self, compression: Compression) -> Vec<u8> {
+        match self {
+            Version::Keyframe(content) => compression.compress(content),
+            Version::Delta(patch) if compression == Compression::None => patch.to_bytes(),
+            Version::Delta(patch) => patch.to_compressed_bytes(compression),
+        }
+    }
+
+    fn try_from_bytes(sign: u8, bytes: &[u8], compression: Compression) -> Result<Self> {
+        match sign {
+            KEYFRAME_SIGN if compression == Compression::None => {
+                Ok(Version::Keyframe(bytes.to_vec()))
+            }
+            KEYFRAME_SIGN => Ok(Version::Keyframe(
+                compression.decompress(bytes, MAX_DECOMPRESSED_LENGTH)?,
+            )),
+            DELTA_SIGN => Ok(Version::Delta(Patch::try_from_bytes(bytes)?)),
+            _ => Err(InstructionError::InvalidSign),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct VersionStore {
+    versions: Vec<Version>,
+    depths: Vec<usize>,
+    stored_length: usize,
+    latest: Vec<u8>,
+    max_depth: usize,
+    compression: Compression,
+}
+
+impl VersionStore {
+    pub fn new(max_depth: usize) -> Self {
+        Self {
+            versions: Vec::new(),
+            depths: Vec::new(),
+            stored_length: 0,
+            latest: Vec::new(),
+            max_depth,
+            compression: Compression::None,
+        }
+    }
+
+    pub fn compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    pub fn len(&self) -> usize {
+        self.versions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.versions.is_empty()
+    }
+
+    pub fn depth(&self, version: usize) -> Option<usize> {
+        self.depths.get(version).copied()
+    }
+
+    pub fn is_keyframe(&self, version: usize) -> bool {
+        self.depth(version) == Some(0)
+    }
+
+    pub fn stored_length(&self) -> usize {
+        self.stored_length
+    }
+
+    pub fn push(&mut self, content: &[u8]) -> usize {
+        let depth = self.depths.last().map_or(0, |depth| depth + 1);
+        let delta = (!self.versions.is_empty() && depth <= self.max_depth)
+            .then(|| Patch::new(&self.latest, content))
+            .filter(|patch| {
+                patch.instructions().iter().any(|instruction| {
+                    matches!(
+                        instruction,
+                        DeltaInstruction::Copy(_) | DeltaInstruction::OffsetCopy(_)
+                    ) && !instruction.is_empty()
+                })
+            });
+        let (version, depth) = match delta {
+            Some(patch) => (Version::Delta(patch), depth),
+            None => (Version::Keyframe(content.to_vec()), 0),
+        };
+        self.stored_length += version.to_bytes(self.compression).len();
+        self.versions.push(version);
+        self.depths.push(depth);
+        self.latest = content.to_vec();
+        self.versions.len() - 1
+    }
+
+    pub fn get(&self, version: usize) -> Option<Vec<u8>> {
+        if self.versions.len().checked_sub(1) == Some(version) {
+            return Some(self.latest.clone());
+        }
+        let keyframe = version.checked_sub(self.depth(version)?)?;
+        let Version::Keyframe(content) = &self.versions[keyframe] else {
+            return None;
+        };
+        let mut content = content.clone();
+        for delta in self.versions[keyframe + 1..=version].iter() {
+            let Version::Delta(patch) = delta else {
+                return None;
+            };
+            content = patch.apply(&content)?;
+        }
+        Some(content)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = Vec::new();
+        bytes.extend_from_slice(STORE_MAGIC);
+        bytes.push(STORE_VERSION);
+        bytes.push(self.compression.byte_sign());
+        bytes.extend((self.max_depth as u64).to_be_bytes());
+        for version in self.versions.iter() {
+            bytes.push(match version {
+                Version::Keyframe(_) => KEYFRAME_SIGN,
+                Version::Delta(_) => DELTA_SIGN,
+            });
+            let content = version.to_bytes(self.compression);
+            bytes.extend((content.len() as u64).to_be_bytes());
+            bytes.extend_from_slice(&content);
+        }
+        bytes
+    }
+
+    pub fn try_from_bytes(bytes: &[u8]) -> Result<Self> {
+        let (compression, bytes) = bytes
+            .strip_prefix(STORE_MAGIC)
+            .and_then(|bytes| bytes.strip_prefix(&[STORE_VERSION]))
+            .and_then(|bytes| bytes.split_first())
+            .ok_or(InstructionError::InvalidHeader)?;
+        let compression = Compression::try_from_byte_sign(*compression)?;
+        let (max_depth, mut bytes) = bytes
+            .split_first_chunk::<8>()
+            .ok_or(InstructionError::InvalidHeader)?;
+        let max_depth = usize::try_from(u64::from_be_bytes(*max_depth)).unwrap_or(usize::MAX);
+        let mut store = Self::new(max_depth).compression(compression);
+        while let Some((sign, rest)) = bytes.split_first() {
+            bytes = rest;
+            let content = read_field(&mut bytes)?;
+            let version = Version::try_from_bytes(*sign, content, compression)?;
+            let depth = match &version {
+                Version::Keyframe(content) => {
+                    store.latest = content.clone();
+                    0
+                }
+                Version::Delta(patch) => {
+                    let depth = store
+                        .depths
+                        .last()
+                        .map(|depth| depth + 1)
+                        .filter(|depth| *depth <= max_depth)
+                        .ok_or(InstructionError::InvalidContent)?;
+                    store.latest = patch
+                        .apply(&store.latest)
+                        .ok_or(InstructionError::SourceMismatch)?;
+                    depth
+                }
+            };
+            store.stored_length += content.len();
+            store.versions.push(version);
+            store.depths.push(depth);
+        }
+        Ok(store)
+    }
+}
+
+impl Default for VersionStore {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_DEPTH)
+    }
+}
+
+#[cfg(test)]
+mod store_tests {
+    use std::fs;
+
+    use super::*;
+
+    fn versions() -> Vec<Vec<u8>> {
+        let mut content = fs::read("files/source.txt").unwrap();
+        let mut versions = vec![content.clone()];
+        for index in 0..9u8 {
+            content[index as usize * 7] = b'A' + index;
+            content.extend_from_slice(&[b'0' + index; 3]);
+            versions.push(content.clone());
+        }
+        versions
+    }
+
+    #[test]
+    fn push_and_get() {
+        let versions = versions();
+        let mut store = VersionStore::new(3);
+        for (index, content) in versions.iter().enumerate() {
+            assert_eq!(store.push(content), index);
+        }
+        assert_eq!(store.len(), versions.len());
+        assert_eq!(
+            (0..store.len())
+                .map(|version| store.depth(version).unwrap())
+                .collect::<Vec<_>>(),
+            [0, 1, 2, 3, 0, 1, 2, 3, 0, 1]
+        );
+        assert!(store.is_keyframe(4));
+        for (index, content) in versions.iter().enumerate() {
+            assert_eq!(store.get(index).as_ref(), Some(content));
+        }
+        assert_eq!(store.get(versions.len()), None);
+        assert_eq!(store.depth(versions.len()), None);
+
+        let mut keyframes = VersionStore::new(0);
+        for content in versions.iter() {
+            keyframes.push(content);
+        }
+        assert!((0..keyframes.len()).all(|version| keyframes.is_keyframe(version)));
+        assert_eq!(
+            keyframes.stored_length(),
+            versions.iter().map(Vec::len).sum::<usize>()
+        );
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn compressed() {
+        let versions = versions();
+        let mut store = VersionStore::new(4).compression(Compression::Zstd);
+        for content in versions.iter() {
+            store.push(content);
+        }
+        assert!(store.stored_length() < versions.iter().map(Vec::len).sum::<usize>() / 2);
+        let restored = VersionStore::try_from_bytes(&store.to_bytes()).unwrap();
+        assert_eq!(restored, store);
+        assert_eq!(restored.get(7).as_ref(), Some(&versions[7]));
+
+        let mut keyframes = VersionStore::new(0).compression(Compression::Zstd);
+        for content in versions.iter() {
+            keyframes.push(content);
+        }
+        assert!(keyframes.stored_length() < versions.iter().map(Vec::len).sum::<usize>() / 2);
+        assert_eq!(
+            VersionStore::try_from_bytes(&keyframes.to_bytes()).unwrap(),
+            keyframes
+        );
+    }
+
+    #[test]
+    fn unrelated_versions() {
+        let mut store = VersionStore::default();
+        store.push(b"ABCDEFGH");
+        store.push(b"12345678");
+        store.push(b"");
+        store.push(b"12345678");
+        assert_eq!(
+            (0..store.len())
+                .map(|version| store.is_keyframe(version))
+                .collect::<Vec<_>>(),
+            [true, true, true, true]
+        );
+        assert_eq!(store.get(1), Some(b"12345678".to_vec()));
+        assert_eq!(store.get(2), Some(Vec::new()));
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        let mut store = VersionStore::new(2);
+        for content in versions().iter() {
+            store.push(content);
+        }
+        let bytes = store.to_bytes();
+        assert_eq!(VersionStore::try_from_bytes(&bytes).unwrap(), store);
+        assert_eq!(
+            VersionStore::try_from_bytes(&VersionStore::default().to_bytes()).unwrap(),
+            VersionStore::default()
+        );
+
+        assert_eq!(
+            VersionStore::try_from_bytes(&bytes[..12]),
+            Err(InstructionError::InvalidHeader)
+        );
+        let mut unknown_compression = bytes.clone();
+        unknown_compression[STORE_MAGIC.len() + 1] = u8::MAX;
+        assert_eq!(
+            VersionStore::try_from_bytes(&unknown_compression),
+            Err(InstructionError::UnsupportedCompression)
+        );
+        assert_eq!(
+            VersionStore::try_from_bytes(&bytes[..bytes.len() - 1]),
+            Err(InstructionError::MissingContent)
+        );
+        let mut delta_first = VersionStore::default().to_bytes();
+        delta_first.push(DELTA_SIGN);
+        delta_first.extend(0u64.to_be_bytes());
+        assert_eq!(
+            VersionStore::try_from_bytes(&delta_first),
+            Err(InstructionError::InvalidContent)
+        );
+        let mut too_deep = bytes.clone();
+        too_deep[STORE_MAGIC.len() + 2..STORE_MAGIC.len() + 10]
+            .copy_from_slice(&1u64.to_be_bytes());
+        assert_eq!(
+            VersionStore::try_from_bytes(&too_deep),
+            Err(InstructionError::InvalidContent)
+        );
+        let mut mismatch = VersionStore::default().to_bytes();
+        mismatch.push(KEYFRAME_SIGN);
+        mismatch.extend(1u64.to_be_bytes());
+        mismatch.push(b'A');
+        mismatch.push(DELTA_SIGN);
+        mismatch.extend(2u64.to_be_bytes());
+        mismatch.extend(b"-\x02");
+        assert_eq!(
+            VersionStore::try_from_bytes(&mismatch),
+            Err(InstructionError::SourceMismatch)
+        );
+    }
+}
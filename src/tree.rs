@@ -0,0 +1,988 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs::{self, File},
+    io,
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    container::read_field,
+    crc32::crc32,
+    instructions::{InstructionError, Result},
+    patch::Patch,
+};
+
+const TREE_MAGIC: &[u8; 4] = b"DLTT";
+const TREE_VERSION: u8 = 2;
+const ADDED_SIGN: u8 = b'+';
+const DELETED_SIGN: u8 = b'-';
+const MODIFIED_SIGN: u8 = b'~';
+const RENAMED_SIGN: u8 = b'>';
+const SYMLINK_SIGN: u8 = b'@';
+const DIRECTORY_SIGN: u8 = b'/';
+const METADATA_SIGN: u8 = b'=';
+const MTIMES_FLAG: u8 = 1;
+const MODE_FLAG: u8 = 1;
+const MTIME_FLAG: u8 = 2;
+const MODE_MASK: u32 = 0o7777;
+const RENAME_SIMILARITY: usize = 50;
+const CHUNK_LENGTH: usize = 64;
+
+fn utf8_error(name: impl std::fmt::Debug) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("{:?} isn't a valid UTF-8 path", name),
+    )
+}
+
+fn symlink_error(path: impl std::fmt::Debug) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("{:?} would be written through a symlink", path),
+    )
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct Metadata {
+    pub mode: Option<u32>,
+    pub mtime: Option<SystemTime>,
+}
+
+impl Metadata {
+    fn read(metadata: &fs::Metadata, mtimes: bool) -> Self {
+        #[cfg(unix)]
+        let mode = {
+            use std::os::unix::fs::PermissionsExt;
+            Some(metadata.permissions().mode() & MODE_MASK)
+        };
+        #[cfg(not(unix))]
+        let mode = None;
+        Self {
+            mode,
+            mtime: metadata
+                .modified()
+                .ok()
+                .filter(|mtime| mtimes && mtime.duration_since(UNIX_EPOCH).is_ok()),
+        }
+    }
+
+    fn restore(&self, path: &Path) -> io::Result<()> {
+        if let Some(mtime) = self.mtime {
+            File::open(path)?.set_modified(mtime)?;
+        }
+        #[cfg(unix)]
+        if let Some(mode) = self.mode {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(path, fs::Permissions::from_mode(mode))?;
+        }
+        Ok(())
+    }
+
+    fn push_bytes(&self, bytes: &mut Vec<u8>) {
+        let mtime = self
+            .mtime
+            .and_then(|mtime| mtime.duration_since(UNIX_EPOCH).ok());
+        let mut flags = 0;
+        if self.mode.is_some() {
+            flags |= MODE_FLAG;
+        }
+        if mtime.is_some() {
+            flags |= MTIME_FLAG;
+        }
+        bytes.push(flags);
+        if let Some(mode) = self.mode {
+            bytes.extend(mode.to_be_bytes());
+        }
+        if let Some(mtime) = mtime {
+            bytes.extend(mtime.as_secs().to_be_bytes());
+            bytes.extend(mtime.subsec_nanos().to_be_bytes());
+        }
+    }
+
+    fn try_from_bytes(bytes: &mut &[u8]) -> Result<Self> {
+        let flags = read_array::<1>(bytes)?[0];
+        if flags & !(MODE_FLAG | MTIME_FLAG) != 0 {
+            return Err(InstructionError::InvalidContent);
+        }
+        let mut metadata = Metadata::default();
+        if flags & MODE_FLAG != 0 {
+            let mode = u32::from_be_bytes(read_array(bytes)?);
+            if mode & !MODE_MASK != 0 {
+                return Err(InstructionError::InvalidContent);
+            }
+            metadata.mode = Some(mode);
+        }
+        if flags & MTIME_FLAG != 0 {
+            let seconds = u64::from_be_bytes(read_array(bytes)?);
+            let nanos = u32::from_be_bytes(read_array(bytes)?);
+            if nanos >= 1_000_000_000 {
+                return Err(InstructionError::InvalidContent);
+            }
+            metadata.mtime = Some(
+                UNIX_EPOCH
+                    .checked_add(Duration::new(seconds, nanos))
+                    .ok_or(InstructionError::InvalidContent)?,
+            );
+        }
+        Ok(metadata)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum TreeChange {
+    Added {
+        path: String,
+        content: Vec<u8>,
+    },
+    Deleted {
+        path: String,
+    },
+    Modified {
+        path: String,
+        patch: Patch,
+    },
+    Renamed {
+        from: String,
+        to: String,
+        patch: Patch,
+    },
+    Symlink {
+        path: String,
+        target: String,
+    },
+    Directory {
+        path: String,
+    },
+    Metadata {
+        path: String,
+        metadata: Metadata,
+    },
+}
+
+impl TreeChange {
+    fn sign(&self) -> u8 {
+        match self {
+            TreeChange::Added { .. } => ADDED_SIGN,
+            TreeChange::Deleted { .. } => DELETED_SIGN,
+            TreeChange::Modified { .. } => MODIFIED_SIGN,
+            TreeChange::Renamed { .. } => RENAMED_SIGN,
+            TreeChange::Symlink { .. } => SYMLINK_SIGN,
+            TreeChange::Directory { .. } => DIRECTORY_SIGN,
+            TreeChange::Metadata { .. } => METADATA_SIGN,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+enum Node {
+    File(Vec<u8>),
+    Symlink(String),
+    Directory,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+struct Entry {
+    node: Node,
+    metadata: Metadata,
+}
+
+impl Entry {
+    fn new(node: Node) -> Self {
+        Self {
+            node,
+            metadata: Metadata::default(),
+        }
+    }
+
+    fn write(&self, path: &Path) -> io::Result<()> {
+        if fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_symlink()) {
+            return Err(symlink_error(path));
+        }
+        match &self.node {
+            Node::File(content) => fs::write(path, content),
+            Node::Directory => fs::create_dir_all(path),
+            #[cfg(unix)]
+            Node::Symlink(target) => std::os::unix::fs::symlink(target, path),
+            #[cfg(not(unix))]
+            Node::Symlink(_) => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "symlinks are only restored on Unix",
+            )),
+        }
+    }
+}
+
+fn inside_symlink(entries: &BTreeMap<String, Entry>, root: &Path, path: &str) -> bool {
+    path.match_indices('/').any(|(index, _)| {
+        let ancestor = &path[..index];
+        matches!(
+            entries.get(ancestor).map(|entry| &entry.node),
+            Some(Node::Symlink(_))
+        ) || fs::symlink_metadata(root.join(ancestor))
+            .is_ok_and(|metadata| metadata.file_type().is_symlink())
+    })
+}
+
+fn read_tree(root: &Path, mtimes: bool) -> io::Result<BTreeMap<String, Entry>> {
+    let mut entries: BTreeMap<String, Entry> = BTreeMap::new();
+    let mut directories = vec![String::new()];
+    while let Some(directory) = directories.pop() {
+        for entry in fs::read_dir(root.join(&directory))? {
+            let entry = entry?;
+            let name = entry.file_name().into_string().map_err(utf8_error)?;
+            let path = if directory.is_empty() {
+                name
+            } else {
+                format!("{}/{}", directory, name)
+            };
+            let file_type = entry.file_type()?;
+            let node = if file_type.is_dir() {
+                directories.push(path.clone());
+                Node::Directory
+            } else if file_type.is_symlink() {
+                let target = fs::read_link(entry.path())?;
+                let target = target.into_os_string().into_string().map_err(utf8_error)?;
+                entries.insert(path, Entry::new(Node::Symlink(target)));
+                continue;
+            } else if file_type.is_file() {
+                Node::File(fs::read(entry.path())?)
+            } else {
+                continue;
+            };
+            let metadata = Metadata::read(&entry.metadata()?, mtimes);
+            entries.insert(path, Entry { node, metadata });
+        }
+    }
+    Ok(entries)
+}
+
+fn unmatched_files<'a>(
+    entries: &'a BTreeMap<String, Entry>,
+    other: &BTreeMap<String, Entry>,
+) -> Vec<(&'a String, &'a Vec<u8>)> {
+    entries
+        .iter()
+        .filter(|(path, _)| !other.contains_key(*path))
+        .filter_map(|(path, entry)| match &entry.node {
+            Node::File(content) => Some((path, content)),
+            _ => None,
+        })
+        .collect()
+}
+
+fn chunks(bytes: &[u8]) -> HashMap<u32, usize> {
+    let mut chunks: HashMap<u32, usize> = HashMap::new();
+    for line in bytes.split_inclusive(|byte| *byte == b'\n') {
+        for chunk in line.chunks(CHUNK_LENGTH) {
+            *chunks.entry(crc32(chunk)).or_default() += chunk.len();
+        }
+    }
+    chunks
+}
+
+fn similarity(source: &HashMap<u32, usize>, target: &HashMap<u32, usize>, length: usize) -> usize {
+    let common = source
+        .iter()
+        .filter_map(|(hash, count)| target.get(hash).map(|other| *count.min(other)))
+        .sum::<usize>();
+    common * 200 / length
+}
+
+fn detect_renames(
+    deleted: &[(&String, &Vec<u8>)],
+    added: &[(&String, &Vec<u8>)],
+) -> Vec<(usize, usize)> {
+    let deleted_chunks: Vec<HashMap<u32, usize>> =
+        deleted.iter().map(|(_, content)| chunks(content)).collect();
+    let added_chunks: Vec<HashMap<u32, usize>> =
+        added.iter().map(|(_, content)| chunks(content)).collect();
+    let mut candidates: Vec<(usize, usize, usize)> = Vec::new();
+    for (source_index, (_, source)) in deleted.iter().enumerate() {
+        for (target_index, (_, target)) in added.iter().enumerate() {
+            if source.is_empty() || target.is_empty() {
+                continue;
+            }
+            let score = if source == target {
+                100
+            } else {
+                similarity(
+                    &deleted_chunks[source_index],
+                    &added_chunks[target_index],
+                    source.len() + target.len(),
+                )
+            };
+            if score >= RENAME_SIMILARITY {
+                candidates.push((score, source_index, target_index));
+            }
+        }
+    }
+    candidates.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)).then(a.2.cmp(&b.2)));
+
+    let mut used_sources = vec![false; deleted.len()];
+    let mut used_targets = vec![false; added.len()];
+    let mut renames: Vec<(usize, usize)> = Vec::new();
+    for (_, source_index, target_index) in candidates {
+        if used_sources[source_index] || used_targets[target_index] {
+            continue;
+        }
+        used_sources[source_index] = true;
+        used_targets[target_index] = true;
+        renames.push((source_index, target_index));
+    }
+    renames
+}
+
+fn push_field(bytes: &mut Vec<u8>, field: &[u8]) {
+    bytes.extend((field.len() as u64).to_be_bytes());
+    bytes.extend_from_slice(field);
+}
+
+fn read_array<const N: usize>(bytes: &mut &[u8]) -> Result<[u8; N]> {
+    let (array, rest) = bytes
+        .split_first_chunk::<N>()
+        .ok_or(InstructionError::MissingContent)?;
+    *bytes = rest;
+    Ok(*array)
+}
+
+fn read_string(bytes: &mut &[u8]) -> Result<String> {
+    core::str::from_utf8(read_field(bytes)?)
+        .map(String::from)
+        .map_err(|_| InstructionError::InvalidContent)
+}
+
+fn read_path(bytes: &mut &[u8]) -> Result<String> {
+    let path = read_string(bytes)?;
+    if path.is_empty()
+        || path
+            .split('/')
+            .any(|component| matches!(component, "" | "." | ".."))
+    {
+        return Err(InstructionError::InvalidContent);
+    }
+    Ok(path)
+}
+
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct TreePatch {
+    changes: Vec<TreeChange>,
+    mtimes: bool,
+}
+
+impl TreePatch {
+    pub fn new(source: impl AsRef<Path>, target: impl AsRef<Path>) -> io::Result<Self> {
+        Self::diff(source.as_ref(), target.as_ref(), false)
+    }
+
+    pub fn new_with_mtimes(source: impl AsRef<Path>, target: impl AsRef<Path>) -> io::Result<Self> {
+        Self::diff(source.as_ref(), target.as_ref(), true)
+    }
+
+    fn diff(source: &Path, target: &Path, mtimes: bool) -> io::Result<Self> {
+        let source = read_tree(source, mtimes)?;
+        let target = read_tree(target, mtimes)?;
+        let mut changes: Vec<TreeChange> = Vec::new();
+        let mut bases: HashMap<&String, Metadata> = HashMap::new();
+        let mut replaced: HashSet<&String> = HashSet::new();
+        for (path, entry) in target.iter() {
+            let Some(source_entry) = source.get(path) else {
+                continue;
+            };
+            match (&source_entry.node, &entry.node) {
+                (Node::File(source_content), Node::File(content)) if source_content != content => {
+                    changes.push(TreeChange::Modified {
+                        path: path.clone(),
+                        patch: Patch::new(source_content, content),
+                    })
+                }
+                (Node::Symlink(source_target), Node::Symlink(target))
+                    if source_target != target =>
+                {
+                    changes.push(TreeChange::Symlink {
+                        path: path.clone(),
+                        target: target.clone(),
+                    })
+                }
+                (source_node, node)
+                    if core::mem::discriminant(source_node) != core::mem::discriminant(node) =>
+                {
+                    changes.push(TreeChange::Deleted { path: path.clone() });
+                    replaced.insert(path);
+                    continue;
+                }
+                _ => {}
+            }
+            bases.insert(path, source_entry.metadata);
+        }
+
+        let deleted = unmatched_files(&source, &target);
+        let added = unmatched_files(&target, &source);
+        let mut renamed_from: HashSet<&String> = HashSet::new();
+        let mut renamed_to: HashSet<&String> = HashSet::new();
+        for (source_index, target_index) in detect_renames(&deleted, &added) {
+            let (from, source_content) = deleted[source_index];
+            let (to, target_content) = added[target_index];
+            changes.push(TreeChange::Renamed {
+                from: from.clone(),
+                to: to.clone(),
+                patch: Patch::new(source_content, target_content),
+            });
+            bases.insert(to, source[from].metadata);
+            renamed_from.insert(from);
+            renamed_to.insert(to);
+        }
+        for path in source.keys() {
+            if !target.contains_key(path) && !renamed_from.contains(path) {
+                changes.push(TreeChange::Deleted { path: path.clone() });
+            }
+        }
+        for (path, entry) in target.iter() {
+            if (source.contains_key(path) && !replaced.contains(path)) || renamed_to.contains(path)
+            {
+                continue;
+            }
+            changes.push(match &entry.node {
+                Node::File(content) => TreeChange::Added {
+                    path: path.clone(),
+                    content: content.clone(),
+                },
+                Node::Symlink(target) => TreeChange::Symlink {
+                    path: path.clone(),
+                    target: target.clone(),
+                },
+                Node::Directory => TreeChange::Directory { path: path.clone() },
+            });
+        }
+        for (path, entry) in target.iter() {
+            if entry.metadata != bases.get(path).copied().unwrap_or_default() {
+                changes.push(TreeChange::Metadata {
+                    path: path.clone(),
+                    metadata: entry.metadata,
+                });
+            }
+        }
+        Ok(Self { changes, mtimes })
+    }
+
+    pub fn changes(&self) -> &[TreeChange] {
+        &self.changes
+    }
+
+    pub fn apply(&self, source: impl AsRef<Path>, target: impl AsRef<Path>) -> io::Result<()> {
+        let mismatch = || io::Error::from(InstructionError::SourceMismatch);
+        let mut entries = read_tree(source.as_ref(), self.mtimes)?;
+        let mut renamed: Vec<(&String, Entry)> = Vec::new();
+        for change in self.changes.iter() {
+            match change {
+                TreeChange::Deleted { path } => {
+                    entries.remove(path).ok_or_else(mismatch)?;
+                }
+                TreeChange::Modified { path, patch } => match entries.get_mut(path) {
+                    Some(Entry {
+                        node: Node::File(content),
+                        ..
+                    }) => *content = patch.apply(content).ok_or_else(mismatch)?,
+                    _ => return Err(mismatch()),
+                },
+                TreeChange::Renamed { from, to, patch } => match entries.remove(from) {
+                    Some(Entry {
+                        node: Node::File(content),
+                        metadata,
+                    }) => {
+                        let content = patch.apply(&content).ok_or_else(mismatch)?;
+                        renamed.push((
+                            to,
+                            Entry {
+                                node: Node::File(content),
+                                metadata,
+                            },
+                        ));
+                    }
+                    _ => return Err(mismatch()),
+                },
+                _ => {}
+            }
+        }
+        for (path, entry) in renamed {
+            entries.insert(path.clone(), entry);
+        }
+        for change in self.changes.iter() {
+            let (path, node) = match change {
+                TreeChange::Added { path, content } => (path, Node::File(content.clone())),
+                TreeChange::Symlink { path, target } => (path, Node::Symlink(target.clone())),
+                TreeChange::Directory { path } => (path, Node::Directory),
+                _ => continue,
+            };
+            entries.insert(path.clone(), Entry::new(node));
+        }
+        for change in self.changes.iter() {
+            if let TreeChange::Metadata { path, metadata } = change {
+                entries.get_mut(path).ok_or_else(mismatch)?.metadata = *metadata;
+            }
+        }
+
+        let target = target.as_ref();
+        if fs::read_dir(target).is_ok_and(|mut children| children.next().is_some()) {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{:?} is not empty", target),
+            ));
+        }
+        fs::create_dir_all(target)?;
+        if let Some(path) = entries
+            .keys()
+            .find(|path| inside_symlink(&entries, target, path))
+        {
+            return Err(symlink_error(path));
+        }
+        let (symlinks, others): (Vec<_>, Vec<_>) = entries
+            .iter()
+            .partition(|(_, entry)| matches!(entry.node, Node::Symlink(_)));
+        for (path, entry) in others.into_iter().chain(symlinks) {
+            let path = target.join(path);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            entry.write(&path)?;
+        }
+        for (path, entry) in entries.iter().rev() {
+            if !matches!(entry.node, Node::Symlink(_)) {
+                entry.metadata.restore(&target.join(path))?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = Vec::new();
+        bytes.extend_from_slice(TREE_MAGIC);
+        bytes.push(TREE_VERSION);
+        bytes.push(if self.mtimes { MTIMES_FLAG } else { 0 });
+        for change in self.changes.iter() {
+            bytes.push(change.sign());
+            match change {
+                TreeChange::Added { path, content } => {
+                    push_field(&mut bytes, path.as_bytes());
+                    push_field(&mut bytes, content);
+                }
+                TreeChange::Deleted { path } | TreeChange::Directory { path } => {
+                    push_field(&mut bytes, path.as_bytes())
+                }
+                TreeChange::Modified { path, patch } => {
+                    push_field(&mut bytes, path.as_bytes());
+                    push_field(&mut bytes, &patch.to_bytes());
+                }
+                TreeChange::Renamed { from, to, patch } => {
+                    push_field(&mut bytes, from.as_bytes());
+                    push_field(&mut bytes, to.as_bytes());
+                    push_field(&mut bytes, &patch.to_bytes());
+                }
+                TreeChange::Symlink { path, target } => {
+                    push_field(&mut bytes, path.as_bytes());
+                    push_field(&mut bytes, target.as_bytes());
+                }
+                TreeChange::Metadata { path, metadata } => {
+                    push_field(&mut bytes, path.as_bytes());
+                    metadata.push_bytes(&mut bytes);
+                }
+            }
+        }
+        bytes
+    }
+
+    pub fn try_from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut bytes = bytes
+            .strip_prefix(TREE_MAGIC)
+            .ok_or(InstructionError::InvalidHeader)?;
+        let mtimes = match read_array::<1>(&mut bytes).map(|version| version[0]) {
+            Ok(1) => false,
+            Ok(TREE_VERSION) => match read_array::<1>(&mut bytes)?[0] {
+                0 => false,
+                MTIMES_FLAG => true,
+                _ => return Err(InstructionError::InvalidHeader),
+            },
+            _ => return Err(InstructionError::InvalidHeader),
+        };
+        let mut changes: Vec<TreeChange> = Vec::new();
+        while let Some((sign, rest)) = bytes.split_first() {
+            bytes = rest;
+            let change = match *sign {
+                ADDED_SIGN => TreeChange::Added {
+                    path: read_path(&mut bytes)?,
+                    content: read_field(&mut bytes)?.to_vec(),
+                },
+                DELETED_SIGN => TreeChange::Deleted {
+                    path: read_path(&mut bytes)?,
+                },
+                MODIFIED_SIGN => TreeChange::Modified {
+                    path: read_path(&mut bytes)?,
+                    patch: Patch::try_from_bytes(read_field(&mut bytes)?)?,
+                },
+                RENAMED_SIGN => TreeChange::Renamed {
+                    from: read_path(&mut bytes)?,
+                    to: read_path(&mut bytes)?,
+                    patch: Patch::try_from_bytes(read_field(&mut bytes)?)?,
+                },
+                SYMLINK_SIGN => TreeChange::Symlink {
+                    path: read_path(&mut bytes)?,
+                    target: read_string(&mut bytes)?,
+                },
+                DIRECTORY_SIGN => TreeChange::Directory {
+                    path: read_path(&mut bytes)?,
+                },
+                METADATA_SIGN => TreeChange::Metadata {
+                    path: read_path(&mut bytes)?,
+                    metadata: Metadata::try_from_bytes(&mut bytes)?,
+                },
+                _ => return Err(InstructionError::InvalidSign),
+            };
+            changes.push(change);
+        }
+        Ok(Self { changes, mtimes })
+    }
+}
+
+#[cfg(test)]
+mod tree_tests {
+    use std::{env, path::PathBuf};
+
+    use super::*;
+
+    fn temporary_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("deltas_tree_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write_tree(root: &Path, files: &[(&str, &[u8])]) {
+        for (path, content) in files {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+    }
+
+    #[test]
+    fn diff_and_apply() {
+        let source_text = fs::read("files/source.txt").unwrap();
+        let target_text = fs::read("files/target.txt").unwrap();
+        let mut moved_text = target_text.clone();
+        moved_text.extend_from_slice(b"one more line\n");
+
+        let dir = temporary_dir("diff_and_apply");
+        let (source, target, output) = (dir.join("old"), dir.join("new"), dir.join("out"));
+        write_tree(
+            &source,
+            &[
+                ("same.txt", b"unchanged"),
+                ("bin/app", &source_text),
+                ("docs/old_name.txt", &target_text),
+                ("removed.txt", b"gone"),
+            ],
+        );
+        write_tree(
+            &target,
+            &[
+                ("same.txt", b"unchanged"),
+                ("bin/app", &target_text),
+                ("docs/guide/new_name.txt", &moved_text),
+                ("new.txt", b"fresh"),
+            ],
+        );
+
+        let patch = TreePatch::new(&source, &target).unwrap();
+        let changes: Vec<&TreeChange> = patch
+            .changes()
+            .iter()
+            .filter(|change| !matches!(change, TreeChange::Metadata { .. }))
+            .collect();
+        assert_eq!(
+            changes,
+            [
+                &TreeChange::Modified {
+                    path: String::from("bin/app"),
+                    patch: Patch::new(&source_text, &target_text)
+                },
+                &TreeChange::Renamed {
+                    from: String::from("docs/old_name.txt"),
+                    to: String::from("docs/guide/new_name.txt"),
+                    patch: Patch::new(&target_text, &moved_text)
+                },
+                &TreeChange::Deleted {
+                    path: String::from("removed.txt")
+                },
+                &TreeChange::Directory {
+                    path: String::from("docs/guide")
+                },
+                &TreeChange::Added {
+                    path: String::from("new.txt"),
+                    content: b"fresh".to_vec()
+                },
+            ]
+        );
+
+        patch.apply(&source, &output).unwrap();
+        assert_eq!(
+            read_tree(&output, false).unwrap(),
+            read_tree(&target, false).unwrap()
+        );
+
+        let error = patch.apply(&target, dir.join("bad")).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn apply_into_existing() {
+        let dir = temporary_dir("apply_into_existing");
+        let (source, target, output) = (dir.join("old"), dir.join("new"), dir.join("out"));
+        write_tree(&source, &[("a.txt", b"A"), ("b.txt", b"B")]);
+        write_tree(&target, &[("a.txt", b"AA")]);
+        let patch = TreePatch::new(&source, &target).unwrap();
+
+        write_tree(&output, &[("stale.txt", b"stale")]);
+        let error = patch.apply(&source, &output).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(fs::read(output.join("stale.txt")).unwrap(), b"stale");
+        assert!(!output.join("a.txt").exists());
+
+        fs::remove_file(output.join("stale.txt")).unwrap();
+        patch.apply(&source, &output).unwrap();
+        assert_eq!(
+            read_tree(&output, false).unwrap(),
+            read_tree(&target, false).unwrap()
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlink_escape() {
+        let dir = temporary_dir("symlink_escape");
+        let (source, output, outside) = (dir.join("old"), dir.join("out"), dir.join("outside"));
+        fs::create_dir_all(&source).unwrap();
+        fs::create_dir_all(&outside).unwrap();
+        let patch = TreePatch {
+            changes: vec![
+                TreeChange::Symlink {
+                    path: String::from("a"),
+                    target: outside.to_string_lossy().into_owned(),
+                },
+                TreeChange::Added {
+                    path: String::from("a/passwd"),
+                    content: b"escaped".to_vec(),
+                },
+            ],
+            mtimes: false,
+        };
+        let error = patch.apply(&source, &output).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(!outside.join("passwd").exists());
+
+        fs::create_dir_all(&output).unwrap();
+        std::os::unix::fs::symlink(&outside, output.join("b")).unwrap();
+        let patch = TreePatch {
+            changes: vec![TreeChange::Added {
+                path: String::from("b/passwd"),
+                content: b"escaped".to_vec(),
+            }],
+            mtimes: false,
+        };
+        let error = patch.apply(&source, &output).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::AlreadyExists);
+        assert!(!outside.join("passwd").exists());
+
+        std::os::unix::fs::symlink(outside.join("passwd"), output.join("c")).unwrap();
+        let error = Entry::new(Node::File(b"escaped".to_vec()))
+            .write(&output.join("c"))
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(!outside.join("passwd").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn metadata() {
+        use std::os::unix::fs::{symlink, PermissionsExt};
+
+        let dir = temporary_dir("metadata");
+        let (source, target, output) = (dir.join("old"), dir.join("new"), dir.join("out"));
+        write_tree(
+            &source,
+            &[("bin/run", b"#!/bin/sh\n"), ("config", b"a = 1\n")],
+        );
+        write_tree(
+            &target,
+            &[("bin/run", b"#!/bin/sh\n"), ("config.d/base", b"a = 1\n")],
+        );
+        fs::set_permissions(target.join("bin/run"), fs::Permissions::from_mode(0o755)).unwrap();
+        fs::set_permissions(target.join("bin"), fs::Permissions::from_mode(0o750)).unwrap();
+        fs::create_dir_all(target.join("cache/empty")).unwrap();
+        symlink("config.d/base", target.join("config")).unwrap();
+        symlink("run", target.join("bin/start")).unwrap();
+        File::open(target.join("config.d/base"))
+            .unwrap()
+            .set_modified(UNIX_EPOCH + Duration::new(1_000_000_000, 5))
+            .unwrap();
+
+        let patch = TreePatch::new(&source, &target).unwrap();
+        assert!(patch.changes().contains(&TreeChange::Metadata {
+            path: String::from("bin/run"),
+            metadata: Metadata {
+                mode: Some(0o755),
+                mtime: None
+            }
+        }));
+        assert!(patch.changes().contains(&TreeChange::Deleted {
+            path: String::from("config")
+        }));
+        assert!(patch.changes().contains(&TreeChange::Symlink {
+            path: String::from("config"),
+            target: String::from("config.d/base")
+        }));
+        assert!(patch.changes().contains(&TreeChange::Directory {
+            path: String::from("cache/empty")
+        }));
+        patch.apply(&source, &output).unwrap();
+        assert_eq!(
+            read_tree(&output, false).unwrap(),
+            read_tree(&target, false).unwrap()
+        );
+
+        let patch = TreePatch::new_with_mtimes(&source, &target).unwrap();
+        let patch = TreePatch::try_from_bytes(&patch.to_bytes()).unwrap();
+        fs::remove_dir_all(&output).unwrap();
+        patch.apply(&source, &output).unwrap();
+        let restored = read_tree(&output, true).unwrap();
+        assert_eq!(restored, read_tree(&target, true).unwrap());
+        assert_eq!(
+            restored["config.d/base"].metadata.mtime,
+            Some(UNIX_EPOCH + Duration::new(1_000_000_000, 5))
+        );
+        assert!(fs::symlink_metadata(output.join("bin/start"))
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn renames() {
+        let a: &[u8] = b"first line\nsecond line\nthird line\n";
+        let b: &[u8] = b"alpha\nbeta\ngamma\ndelta\n";
+        let deleted = [
+            (&String::from("a"), &a.to_vec()),
+            (&String::from("b"), &b.to_vec()),
+        ];
+        let added = [
+            (
+                &String::from("c"),
+                &b"alpha\nbeta\ngamma\nepsilon\n".to_vec(),
+            ),
+            (&String::from("d"), &b"unrelated content\n".to_vec()),
+            (&String::from("e"), &a.to_vec()),
+        ];
+        assert_eq!(detect_renames(&deleted, &added), [(0, 2), (1, 0)]);
+        assert_eq!(detect_renames(&deleted[..1], &added[..2]), []);
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        let patch = TreePatch {
+            changes: vec![
+                TreeChange::Added {
+                    path: String::from("a/b"),
+                    content: b"AB".to_vec(),
+                },
+                TreeChange::Deleted {
+                    path: String::from("c"),
+                },
+                TreeChange::Modified {
+                    path: String::from("d"),
+                    patch: Patch::new(b"ABC", b"ABD"),
+                },
+                TreeChange::Renamed {
+                    from: String::from("e"),
+                    to: String::from("f/e"),
+                    patch: Patch::new(b"XYZ", b"XYZ"),
+                },
+                TreeChange::Symlink {
+                    path: String::from("g"),
+                    target: String::from("../f/e"),
+                },
+                TreeChange::Directory {
+                    path: String::from("h"),
+                },
+                TreeChange::Metadata {
+                    path: String::from("f/e"),
+                    metadata: Metadata {
+                        mode: Some(0o755),
+                        mtime: Some(UNIX_EPOCH + Duration::new(42, 7)),
+                    },
+                },
+                TreeChange::Metadata {
+                    path: String::from("h"),
+                    metadata: Metadata::default(),
+                },
+            ],
+            mtimes: true,
+        };
+        let bytes = patch.to_bytes();
+        assert_eq!(TreePatch::try_from_bytes(&bytes).unwrap(), patch);
+        assert_eq!(
+            TreePatch::try_from_bytes(&TreePatch::default().to_bytes()).unwrap(),
+            TreePatch::default()
+        );
+
+        let mut version_1 = b"DLTT\x01".to_vec();
+        version_1.push(DELETED_SIGN);
+        push_field(&mut version_1, b"c");
+        assert_eq!(
+            TreePatch::try_from_bytes(&version_1).unwrap(),
+            TreePatch {
+                changes: vec![TreeChange::Deleted {
+                    path: String::from("c")
+                }],
+                mtimes: false
+            }
+        );
+
+        assert_eq!(
+            TreePatch::try_from_bytes(b"DLTZ\x02\x00"),
+            Err(InstructionError::InvalidHeader)
+        );
+        assert_eq!(
+            TreePatch::try_from_bytes(b"DLTT\x02\x04"),
+            Err(InstructionError::InvalidHeader)
+        );
+        assert_eq!(
+            TreePatch::try_from_bytes(&bytes[..bytes.len() - 1]),
+            Err(InstructionError::MissingContent)
+        );
+        let mut unknown = TreePatch::default().to_bytes();
+        unknown.push(b'?');
+        assert_eq!(
+            TreePatch::try_from_bytes(&unknown),
+            Err(InstructionError::InvalidSign)
+        );
+        let mut escaping = TreePatch::default().to_bytes();
+        escaping.push(DELETED_SIGN);
+        push_field(&mut escaping, b"../etc/passwd");
+        assert_eq!(
+            TreePatch::try_from_bytes(&escaping),
+            Err(InstructionError::InvalidContent)
+        );
+        let mut bad_mode = TreePatch::default().to_bytes();
+        bad_mode.push(METADATA_SIGN);
+        push_field(&mut bad_mode, b"a");
+        bad_mode.push(MODE_FLAG);
+        bad_mode.extend(0o170000u32.to_be_bytes());
+        assert_eq!(
+            TreePatch::try_from_bytes(&bad_mode),
+            Err(InstructionError::InvalidContent)
+        );
+    }
+}
@@ -0,0 +1,630 @@
+use alloc::{vec, vec::Vec};
+
+use crate::{
+    builder::PatchBuilder,
+    instructions::{InstructionError, Result},
+    operation::Operation,
+    patch::Patch,
+};
+
+const VCDIFF_MAGIC: &[u8; 4] = &[0xD6, 0xC3, 0xC4, 0x00];
+const VCDIFF_WINDOW_SIZE: usize = 1 << 20;
+const MAX_TARGET_WINDOW_LENGTH: usize = 1 << 26;
+
+const VCD_DECOMPRESS: u8 = 0x01;
+const VCD_CODETABLE: u8 = 0x02;
+const VCD_APPHEADER: u8 = 0x04;
+
+const VCD_SOURCE: u8 = 0x01;
+const VCD_TARGET: u8 = 0x02;
+const VCD_ADLER32: u8 = 0x04;
+
+const NEAR_CACHE_SIZE: usize = 4;
+const SAME_CACHE_SIZE: usize = 3;
+
+const MIN_COPY_LENGTH: usize = 4;
+const MIN_RUN_LENGTH: usize = 8;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum CodeKind {
+    NoOp,
+    Add,
+    Run,
+    Copy(u8),
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+struct Code {
+    kind: CodeKind,
+    size: u8,
+}
+
+impl Code {
+    const NO_OP: Code = Code {
+        kind: CodeKind::NoOp,
+        size: 0,
+    };
+
+    fn new(kind: CodeKind, size: u8) -> Self {
+        Self { kind, size }
+    }
+}
+
+fn default_code_table() -> Vec<(Code, Code)> {
+    let mut table: Vec<(Code, Code)> = Vec::with_capacity(256);
+    table.push((Code::new(CodeKind::Run, 0), Code::NO_OP));
+    for size in 0..=17 {
+        table.push((Code::new(CodeKind::Add, size), Code::NO_OP));
+    }
+    for mode in 0..=8 {
+        table.push((Code::new(CodeKind::Copy(mode), 0), Code::NO_OP));
+        for size in 4..=18 {
+            table.push((Code::new(CodeKind::Copy(mode), size), Code::NO_OP));
+        }
+    }
+    for mode in 0..=5 {
+        for add_size in 1..=4 {
+            for copy_size in 4..=6 {
+                table.push((
+                    Code::new(CodeKind::Add, add_size),
+                    Code::new(CodeKind::Copy(mode), copy_size),
+                ));
+            }
+        }
+    }
+    for mode in 6..=8 {
+        for add_size in 1..=4 {
+            table.push((
+                Code::new(CodeKind::Add, add_size),
+                Code::new(CodeKind::Copy(mode), 4),
+            ));
+        }
+    }
+    for mode in 0..=8 {
+        table.push((
+            Code::new(CodeKind::Copy(mode), 4),
+            Code::new(CodeKind::Add, 1),
+        ));
+    }
+    table
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in bytes.chunks(5552) {
+        for item in chunk.iter() {
+            a += *item as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    (b << 16) | a
+}
+
+fn write_integer(bytes: &mut Vec<u8>, mut value: usize) {
+    let mut buffer = [0u8; 10];
+    let mut index = buffer.len() - 1;
+    buffer[index] = (value & 0x7F) as u8;
+    value >>= 7;
+    while value > 0 {
+        index -= 1;
+        buffer[index] = 0x80 | (value & 0x7F) as u8;
+        value >>= 7;
+    }
+    bytes.extend_from_slice(&buffer[index..]);
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, position: 0 }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.position == self.bytes.len()
+    }
+
+    fn byte(&mut self) -> Result<u8> {
+        let byte = *self
+            .bytes
+            .get(self.position)
+            .ok_or(InstructionError::MissingContent)?;
+        self.position += 1;
+        Ok(byte)
+    }
+
+    fn integer(&mut self) -> Result<usize> {
+        let mut value = 0usize;
+        loop {
+            let byte = self.byte()?;
+            value = value
+                .checked_mul(128)
+                .ok_or(InstructionError::InvalidLength)?
+                | (byte & 0x7F) as usize;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn take(&mut self, length: usize) -> Result<&'a [u8]> {
+        let end = self
+            .position
+            .checked_add(length)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or(InstructionError::MissingContent)?;
+        let bytes = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+}
+
+struct AddressCache {
+    near: [usize; NEAR_CACHE_SIZE],
+    next_slot: usize,
+    same: [usize; SAME_CACHE_SIZE * 256],
+}
+
+impl AddressCache {
+    fn new() -> Self {
+        Self {
+            near: [0; NEAR_CACHE_SIZE],
+            next_slot: 0,
+            same: [0; SAME_CACHE_SIZE * 256],
+        }
+    }
+
+    fn update(&mut self, address: usize) {
+        self.near[self.next_slot] = address;
+        self.next_slot = (self.next_slot + 1) % NEAR_CACHE_SIZE;
+        self.same[address % (SAME_CACHE_SIZE * 256)] = address;
+    }
+
+    fn decode(&mut self, here: usize, mode: u8, addresses: &mut Reader<'_>) -> Result<usize> {
+        let address = match mode as usize {
+            0 => addresses.integer()?,
+            1 => here
+                .checked_sub(addresses.integer()?)
+                .ok_or(InstructionError::InvalidContent)?,
+            mode if mode < 2 + NEAR_CACHE_SIZE => self.near[mode - 2]
+                .checked_add(addresses.integer()?)
+                .ok_or(InstructionError::InvalidContent)?,
+            mode => self.same[(mode - 2 - NEAR_CACHE_SIZE) * 256 + addresses.byte()? as usize],
+        };
+        if address >= here {
+            return Err(InstructionError::InvalidContent);
+        }
+        self.update(address);
+        Ok(address)
+    }
+}
+
+#[derive(Debug, Default)]
+struct WindowEncoder {
+    data: Vec<u8>,
+    instructions: Vec<u8>,
+    addresses: Vec<u8>,
+}
+
+impl WindowEncoder {
+    fn add(&mut self, content: &[u8]) {
+        let mut start = 0;
+        while start < content.len() {
+            let run_length = content[start..]
+                .iter()
+                .take_while(|item| **item == content[start])
+                .count();
+            if run_length >= MIN_RUN_LENGTH {
+                self.instructions.push(0);
+                write_integer(&mut self.instructions, run_length);
+                self.data.push(content[start]);
+                start += run_length;
+                continue;
+            }
+            let end = (start + 1..content.len())
+                .find(|index| {
+                    content[*index..]
+                        .iter()
+                        .take_while(|item| **item == content[*index])
+                        .take(MIN_RUN_LENGTH)
+                        .count()
+                        == MIN_RUN_LENGTH
+                })
+                .unwrap_or(content.len());
+            let length = end - start;
+            if length <= 17 {
+                self.instructions.push(1 + length as u8);
+            } else {
+                self.instructions.push(1);
+                write_integer(&mut self.instructions, length);
+            }
+            self.data.extend_from_slice(&content[start..end]);
+            start = end;
+        }
+    }
+
+    fn copy(&mut self, address: usize, length: usize) {
+        if (MIN_COPY_LENGTH..=18).contains(&length) {
+            self.instructions.push(16 + length as u8);
+        } else {
+            self.instructions.push(19);
+            write_integer(&mut self.instructions, length);
+        }
+        write_integer(&mut self.addresses, address);
+    }
+}
+
+fn write_window(bytes: &mut Vec<u8>, operations: &[Operation]) {
+    let segment = operations
+        .iter()
+        .filter_map(|operation| match operation {
+            Operation::Copy { offset, length } => Some((*offset, *offset + *length)),
+            Operation::Add(_) => None,
+        })
+        .reduce(|(start, end), (offset, copy_end)| (start.min(offset), end.max(copy_end)));
+
+    let mut encoder = WindowEncoder::default();
+    for operation in operations.iter() {
+        match operation {
+            Operation::Add(content) => encoder.add(content),
+            Operation::Copy { offset, length } => {
+                encoder.copy(offset - segment.unwrap().0, *length)
+            }
+        }
+    }
+
+    let target_length: usize = operations.iter().map(Operation::len).sum();
+    let mut delta: Vec<u8> = Vec::new();
+    write_integer(&mut delta, target_length);
+    delta.push(0);
+    write_integer(&mut delta, encoder.data.len());
+    write_integer(&mut delta, encoder.instructions.len());
+    write_integer(&mut delta, encoder.addresses.len());
+    delta.extend(encoder.data);
+    delta.extend(encoder.instructions);
+    delta.extend(encoder.addresses);
+
+    match segment {
+        Some((start, end)) => {
+            bytes.push(VCD_SOURCE);
+            write_integer(bytes, end - start);
+            write_integer(bytes, start);
+        }
+        None => bytes.push(0),
+    }
+    write_integer(bytes, delta.len());
+    bytes.extend(delta);
+}
+
+fn decode_window(
+    reader: &mut Reader<'_>,
+    code_table: &[(Code, Code)],
+    source: &[u8],
+    target: &mut Vec<u8>,
+    builder: &mut PatchBuilder,
+) -> Result<()> {
+    let window_indicator = reader.byte()?;
+    if window_indicator & !(VCD_SOURCE | VCD_TARGET | VCD_ADLER32) != 0
+        || window_indicator & (VCD_SOURCE | VCD_TARGET) == VCD_SOURCE | VCD_TARGET
+    {
+        return Err(InstructionError::InvalidHeader);
+    }
+    let segment = if window_indicator & (VCD_SOURCE | VCD_TARGET) != 0 {
+        let length = reader.integer()?;
+        let offset = reader.integer()?;
+        let available = if window_indicator & VCD_SOURCE != 0 {
+            source.len()
+        } else {
+            target.len()
+        };
+        if offset.checked_add(length).is_none_or(|end| end > available) {
+            return Err(InstructionError::InvalidContent);
+        }
+        Some((window_indicator & VCD_SOURCE != 0, offset, length))
+    } else {
+        None
+    };
+    let segment_length = segment.map_or(0, |(_, _, length)| length);
+
+    let delta_length = reader.integer()?;
+    let mut delta = Reader::new(reader.take(delta_length)?);
+    let target_length = delta.integer()?;
+    if target_length > MAX_TARGET_WINDOW_LENGTH {
+        return Err(InstructionError::InvalidContent);
+    }
+    if delta.byte()? != 0 {
+        return Err(InstructionError::UnsupportedCompression);
+    }
+    let data_length = delta.integer()?;
+    let instructions_length = delta.integer()?;
+    let addresses_length = delta.integer()?;
+    let checksum = if window_indicator & VCD_ADLER32 != 0 {
+        Some(u32::from_be_bytes(delta.take(4)?.try_into().unwrap()))
+    } else {
+        None
+    };
+    let mut data = Reader::new(delta.take(data_length)?);
+    let mut instructions = Reader::new(delta.take(instructions_length)?);
+    let mut addresses = Reader::new(delta.take(addresses_length)?);
+    if !delta.is_empty() {
+        return Err(InstructionError::InvalidContent);
+    }
+
+    let window_start = target.len();
+    let mut cache = AddressCache::new();
+    while !instructions.is_empty() {
+        let (first, second) = code_table[instructions.byte()? as usize];
+        for code in [first, second] {
+            if code.kind == CodeKind::NoOp {
+                continue;
+            }
+            let size = match code.size {
+                0 => instructions.integer()?,
+                size => size as usize,
+            };
+            if size > target_length - (target.len() - window_start) {
+                return Err(InstructionError::InvalidContent);
+            }
+            match code.kind {
+                CodeKind::Add => {
+                    let content = data.take(size)?;
+                    target.extend_from_slice(content);
+                    builder.add(content);
+                }
+                CodeKind::Run => {
+                    let start = target.len();
+                    target.resize(start + size, data.byte()?);
+                    builder.add(&target[start..]);
+                }
+                CodeKind::Copy(mode) => {
+                    let here = segment_length + target.len() - window_start;
+                    let address = cache.decode(here, mode, &mut addresses)?;
+                    match segment {
+                        Some((true, offset, length))
+                            if address.checked_add(size).is_some_and(|end| end <= length) =>
+                        {
+                            target.extend_from_slice(&source[offset + address..][..size]);
+                            builder.copy_from(source, offset + address, &vec![0; size]);
+                            continue;
+                        }
+                        _ => (),
+                    }
+                    let start = target.len();
+                    for index in 0..size {
+                        let item = match (segment, address + index) {
+                            (Some((true, offset, _)), position) if position < segment_length => {
+                                source[offset + position]
+                            }
+                            (Some((false, offset, _)), position) if position < segment_length => {
+                                target[offset + position]
+                            }
+                            (_, position) => target[window_start + position - segment_length],
+                        };
+                        target.push(item);
+                    }
+                    builder.add(&target[start..]);
+                }
+                CodeKind::NoOp => unreachable!(),
+            }
+        }
+    }
+    if target.len() - window_start != target_length || !data.is_empty() || !addresses.is_empty() {
+        return Err(InstructionError::InvalidContent);
+    }
+    if checksum.is_some_and(|checksum| checksum != adler32(&target[window_start..])) {
+        return Err(InstructionError::InvalidChecksum);
+    }
+    Ok(())
+}
+
+impl Patch {
+    pub fn to_vcdiff(&self, source: &[u8]) -> Option<Vec<u8>> {
+        self.to_vcdiff_windows(source, VCDIFF_WINDOW_SIZE)
+    }
+
+    fn to_vcdiff_windows(&self, source: &[u8], window_size: usize) -> Option<Vec<u8>> {
+        let operations = self.operations(source, MIN_COPY_LENGTH)?;
+
+        let mut bytes: Vec<u8> = VCDIFF_MAGIC.to_vec();
+        bytes.push(0);
+        let mut window: Vec<Operation> = Vec::new();
+        let mut window_length = 0;
+        for mut operation in operations.into_iter() {
+            while window_length + operation.len() > window_size {
+                let rest = operation.split_off(window_size - window_length);
+                window.push(operation);
+                write_window(&mut bytes, &window);
+                window.clear();
+                window_length = 0;
+                operation = rest;
+            }
+            window_length += operation.len();
+            window.push(operation);
+        }
+        if !window.is_empty() || bytes.len() == VCDIFF_MAGIC.len() + 1 {
+            write_window(&mut bytes, &window);
+        }
+        Some(bytes)
+    }
+
+    pub fn from_vcdiff(bytes: &[u8], source: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(bytes);
+        if reader.take(VCDIFF_MAGIC.len()).ok() != Some(&VCDIFF_MAGIC[..]) {
+            return Err(InstructionError::InvalidHeader);
+        }
+        let header_indicator = reader.byte()?;
+        if header_indicator & !(VCD_DECOMPRESS | VCD_CODETABLE | VCD_APPHEADER) != 0 {
+            return Err(InstructionError::InvalidHeader);
+        }
+        if header_indicator & VCD_DECOMPRESS != 0 {
+            return Err(InstructionError::UnsupportedCompression);
+        }
+        if header_indicator & VCD_CODETABLE != 0 {
+            return Err(InstructionError::UnsupportedFeature);
+        }
+        if header_indicator & VCD_APPHEADER != 0 {
+            let length = reader.integer()?;
+            reader.take(length)?;
+        }
+
+        let code_table = default_code_table();
+        let mut target: Vec<u8> = Vec::new();
+        let mut builder = PatchBuilder::new();
+        while !reader.is_empty() {
+            decode_window(&mut reader, &code_table, source, &mut target, &mut builder)?;
+        }
+        Ok(builder.finish(source.len()))
+    }
+}
+
+#[cfg(test)]
+mod vcdiff_tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn code_table() {
+        let table = default_code_table();
+        assert_eq!(table.len(), 256);
+        assert_eq!(table[1], (Code::new(CodeKind::Add, 0), Code::NO_OP));
+        assert_eq!(table[35], (Code::new(CodeKind::Copy(1), 0), Code::NO_OP));
+        assert_eq!(
+            table[163],
+            (Code::new(CodeKind::Add, 1), Code::new(CodeKind::Copy(0), 4))
+        );
+        assert_eq!(
+            table[247],
+            (Code::new(CodeKind::Copy(0), 4), Code::new(CodeKind::Add, 1))
+        );
+    }
+
+    #[test]
+    fn integer() {
+        let mut bytes = Vec::new();
+        write_integer(&mut bytes, 123456789);
+        assert_eq!(bytes, [0xBA, 0xEF, 0x9A, 0x15]);
+        assert_eq!(Reader::new(&bytes).integer(), Ok(123456789));
+        assert_eq!(
+            Reader::new(&[0xFF; 11]).integer(),
+            Err(InstructionError::InvalidLength)
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let source = fs::read("files/source.txt").unwrap();
+        let target = fs::read("files/target.txt").unwrap();
+        let patch = Patch::new(&source, &target);
+        for window_size in [VCDIFF_WINDOW_SIZE, 100, 7] {
+            let bytes = patch.to_vcdiff_windows(&source, window_size).unwrap();
+            let decoded = Patch::from_vcdiff(&bytes, &source).unwrap();
+            assert_eq!(decoded.apply(&source).unwrap(), target);
+        }
+        assert_eq!(patch.to_vcdiff(b"too short"), None);
+    }
+
+    #[test]
+    fn run() {
+        let source = b"AAAABBBBCCCC".to_vec();
+        let mut target = source.clone();
+        target.extend([b'Z'; 40]);
+        let patch = Patch::new(&source, &target);
+        let bytes = patch.to_vcdiff(&source).unwrap();
+        assert!(bytes.len() < 40);
+        assert_eq!(
+            Patch::from_vcdiff(&bytes, &source)
+                .unwrap()
+                .apply(&source)
+                .unwrap(),
+            target
+        );
+    }
+
+    #[test]
+    fn from_vcdiff() {
+        // ADD "ab" + COPY 4 from the source segment, then an overlapping VCD_HERE copy.
+        let mut bytes = VCDIFF_MAGIC.to_vec();
+        bytes.push(0);
+        bytes.extend([VCD_SOURCE | VCD_ADLER32, 6, 0]);
+        let target = b"abCDEFEFEFEF";
+        let mut delta = vec![12, 0, 2, 2, 2];
+        delta.extend(adler32(target).to_be_bytes());
+        delta.extend(b"ab");
+        delta.extend([166, 38]);
+        delta.extend([2, 2]);
+        bytes.push(delta.len() as u8);
+        bytes.extend(&delta);
+
+        let patch = Patch::from_vcdiff(&bytes, b"ABCDEF").unwrap();
+        assert_eq!(patch.apply(b"ABCDEF").unwrap(), target);
+
+        let length = bytes.len();
+        bytes[length - 6] ^= 1;
+        assert_eq!(
+            Patch::from_vcdiff(&bytes, b"ABCDEF"),
+            Err(InstructionError::InvalidChecksum)
+        );
+    }
+
+    #[test]
+    fn from_vcdiff_err() {
+        assert_eq!(
+            Patch::from_vcdiff(b"VCD", b""),
+            Err(InstructionError::InvalidHeader)
+        );
+        assert_eq!(
+            Patch::from_vcdiff(&[0xD6, 0xC3, 0xC4, 0x00, VCD_DECOMPRESS], b""),
+            Err(InstructionError::UnsupportedCompression)
+        );
+        assert_eq!(
+            Patch::from_vcdiff(&[0xD6, 0xC3, 0xC4, 0x00, VCD_CODETABLE], b""),
+            Err(InstructionError::UnsupportedFeature)
+        );
+        assert_eq!(
+            Patch::from_vcdiff(&[0xD6, 0xC3, 0xC4, 0x00, 0, VCD_SOURCE, 4, 0], b"AB"),
+            Err(InstructionError::InvalidContent)
+        );
+
+        let mut delta = Vec::new();
+        write_integer(&mut delta, usize::MAX);
+        delta.push(0);
+        write_integer(&mut delta, 1);
+        let mut instructions = vec![2, 1];
+        write_integer(&mut instructions, usize::MAX);
+        write_integer(&mut delta, instructions.len());
+        write_integer(&mut delta, 0);
+        delta.push(b'A');
+        delta.extend(instructions);
+        let mut bytes = vec![0xD6, 0xC3, 0xC4, 0x00, 0, 0];
+        write_integer(&mut bytes, delta.len());
+        bytes.extend(delta);
+        assert_eq!(
+            Patch::from_vcdiff(&bytes, b""),
+            Err(InstructionError::InvalidContent)
+        );
+
+        let mut delta = Vec::new();
+        write_integer(&mut delta, 1 << 40);
+        delta.push(0);
+        write_integer(&mut delta, 1);
+        let mut instructions = vec![0];
+        write_integer(&mut instructions, 1 << 40);
+        write_integer(&mut delta, instructions.len());
+        write_integer(&mut delta, 0);
+        delta.push(b'A');
+        delta.extend(instructions);
+        let mut bytes = vec![0xD6, 0xC3, 0xC4, 0x00, 0, 0];
+        write_integer(&mut bytes, delta.len());
+        bytes.extend(delta);
+        assert_eq!(
+            Patch::from_vcdiff(&bytes, b""),
+            Err(InstructionError::InvalidContent)
+        );
+    }
+}
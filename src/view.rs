@@ -0,0 +1,288 @@
+use alloc::vec::Vec;
+
+use crate::{
+    container,
+    instructions::{
+        add_instruction::AddInstruction, content_length, copy_instruction::CopyInstruction,
+        delta_instruction::DeltaInstruction, offset_copy_instruction::OffsetCopyInstruction,
+        remove_instruction::RemoveInstruction, InstructionError, InstructionInfo, Result,
+        ADD_INSTRUCTION_SIGN, COPY_INSTRUCTION_SIGN, REMOVE_INSTRUCTION_SIGN,
+    },
+    patch::Patch,
+    sink::{write_instruction, Sink},
+};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum InstructionView<'a> {
+    Remove(u8),
+    Add(&'a [u8]),
+    Copy(&'a [u8]),
+    OffsetCopy { offset: u64, length: u8 },
+}
+
+impl InstructionView<'_> {
+    pub fn len(&self) -> u8 {
+        match self {
+            InstructionView::Remove(length) => *length,
+            InstructionView::Add(content) => content.len() as u8,
+            InstructionView::Copy(content) => content.len() as u8,
+            InstructionView::OffsetCopy { length, .. } => *length,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == u8::MIN
+    }
+}
+
+impl<'a> From<&'a DeltaInstruction> for InstructionView<'a> {
+    fn from(value: &'a DeltaInstruction) -> Self {
+        match value {
+            DeltaInstruction::Remove(instruction) => InstructionView::Remove(instruction.len()),
+            DeltaInstruction::Add(instruction) => InstructionView::Add(instruction.content()),
+            DeltaInstruction::Copy(instruction) => InstructionView::Copy(instruction.content()),
+            DeltaInstruction::OffsetCopy(instruction) => InstructionView::OffsetCopy {
+                offset: instruction.offset(),
+                length: instruction.len(),
+            },
+        }
+    }
+}
+
+impl From<InstructionView<'_>> for DeltaInstruction {
+    fn from(value: InstructionView<'_>) -> Self {
+        match value {
+            InstructionView::Remove(length) => RemoveInstruction::new(length).into(),
+            InstructionView::Add(content) => AddInstruction::new(content.to_vec()).into(),
+            InstructionView::Copy(content) => CopyInstruction::new(content.to_vec()).into(),
+            InstructionView::OffsetCopy { offset, length } => {
+                OffsetCopyInstruction::new(offset, length).into()
+            }
+        }
+    }
+}
+
+fn read_instruction(bytes: &[u8]) -> Result<(InstructionView<'_>, &[u8])> {
+    let (sign, bytes) = bytes.split_first().ok_or(InstructionError::MissignSign)?;
+    let (length, bytes) = bytes.split_first().ok_or(InstructionError::MissingLength)?;
+    let content_length = content_length(*sign, *length)?;
+    if bytes.len() < content_length {
+        return Err(InstructionError::MissingContent);
+    }
+    let (content, bytes) = bytes.split_at(content_length);
+    let instruction = match *sign {
+        REMOVE_INSTRUCTION_SIGN => InstructionView::Remove(*length),
+        ADD_INSTRUCTION_SIGN => InstructionView::Add(content),
+        COPY_INSTRUCTION_SIGN => InstructionView::Copy(content),
+        _ => InstructionView::OffsetCopy {
+            offset: u64::from_be_bytes(content.try_into().unwrap()),
+            length: *length,
+        },
+    };
+    Ok((instruction, bytes))
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct PatchView<'a> {
+    bytes: &'a [u8],
+    source_length: usize,
+    target_length: usize,
+    offset_end: u64,
+}
+
+impl<'a> PatchView<'a> {
+    pub fn new(bytes: &'a [u8]) -> Result<Self> {
+        if container::is_container(bytes) {
+            return Err(InstructionError::UnsupportedFeature);
+        }
+        let mut view = Self {
+            bytes,
+            source_length: 0,
+            target_length: 0,
+            offset_end: 0,
+        };
+        let mut remaining = bytes;
+        let mut index = 0usize;
+        while let Some(byte) = remaining.first() {
+            let start = bytes.len() - remaining.len();
+            let (instruction, rest) =
+                read_instruction(remaining).map_err(|error| error.at(index, start, *byte))?;
+            match instruction {
+                InstructionView::Remove(length) => view.source_length += length as usize,
+                InstructionView::Add(content) => view.target_length += content.len(),
+                InstructionView::Copy(content) => {
+                    view.source_length += content.len();
+                    view.target_length += content.len();
+                }
+                InstructionView::OffsetCopy { offset, length } => {
+                    let end = offset
+                        .checked_add(length as u64)
+                        .ok_or_else(|| InstructionError::InvalidContent.at(index, start, *byte))?;
+                    view.offset_end = view.offset_end.max(end);
+                    view.target_length += length as usize;
+                }
+            }
+            remaining = rest;
+            index += 1;
+        }
+        Ok(view)
+    }
+
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    pub fn source_length(&self) -> usize {
+        self.source_length
+    }
+
+    pub fn target_length(&self) -> usize {
+        self.target_length
+    }
+
+    pub fn instructions(&self) -> Instructions<'a> {
+        Instructions { bytes: self.bytes }
+    }
+
+    pub fn apply(&self, source: &[u8]) -> Option<Vec<u8>> {
+        let mut target: Vec<u8> = Vec::with_capacity(self.target_length);
+        self.apply_to(source, &mut target).ok()?;
+        Some(target)
+    }
+
+    pub fn apply_to<S: Sink + ?Sized>(&self, source: &[u8], sink: &mut S) -> Result<()> {
+        if source.len() != self.source_length || self.offset_end > source.len() as u64 {
+            return Err(InstructionError::SourceMismatch);
+        }
+        let mut position = 0usize;
+        for instruction in self.instructions() {
+            write_instruction(instruction, source, &mut position, sink)?;
+        }
+        Ok(())
+    }
+
+    pub fn apply_into(&self, source: &[u8], out: &mut [u8]) -> Result<usize> {
+        if out.len() < self.target_length {
+            return Err(InstructionError::OutputTooSmall {
+                available: out.len(),
+                required: self.target_length,
+            });
+        }
+        self.apply_to(source, &mut &mut out[..])?;
+        Ok(self.target_length)
+    }
+
+    pub fn to_patch(&self) -> Patch {
+        Patch::from_instructions(self.instructions().map(DeltaInstruction::from).collect())
+    }
+}
+
+impl<'a> TryFrom<&'a [u8]> for PatchView<'a> {
+    type Error = InstructionError;
+
+    fn try_from(value: &'a [u8]) -> core::result::Result<Self, Self::Error> {
+        PatchView::new(value)
+    }
+}
+
+impl From<PatchView<'_>> for Patch {
+    fn from(value: PatchView<'_>) -> Self {
+        value.to_patch()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Instructions<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Iterator for Instructions<'a> {
+    type Item = InstructionView<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.bytes.is_empty() {
+            return None;
+        }
+        let (instruction, rest) =
+            read_instruction(self.bytes).expect("Patch view bytes were validated");
+        self.bytes = rest;
+        Some(instruction)
+    }
+}
+
+#[cfg(test)]
+mod view_tests {
+    use std::fs;
+
+    use super::*;
+    use crate::container::Compression;
+
+    #[test]
+    fn instructions() {
+        let mut bytes = b"-\x02+\x02AB|\x02\x00\x01@\x01".to_vec();
+        bytes.extend(1u64.to_be_bytes());
+        let view = PatchView::new(&bytes).unwrap();
+        assert_eq!(
+            view.instructions().collect::<Vec<_>>(),
+            [
+                InstructionView::Remove(2),
+                InstructionView::Add(b"AB"),
+                InstructionView::Copy(&[0, 1]),
+                InstructionView::OffsetCopy {
+                    offset: 1,
+                    length: 1
+                },
+            ]
+        );
+        assert_eq!(view.source_length(), 4);
+        assert_eq!(view.target_length(), 5);
+        assert_eq!(view.apply(b"XYCD").unwrap(), b"ABCEY");
+        assert_eq!(view.apply(b"XYC"), None);
+        let mut out = [0u8; 6];
+        assert_eq!(view.apply_into(b"XYCD", &mut out), Ok(5));
+        assert_eq!(&out, b"ABCEY\0");
+        assert_eq!(
+            view.apply_into(b"XYCD", &mut out[..4]),
+            Err(InstructionError::OutputTooSmall {
+                available: 4,
+                required: 5
+            })
+        );
+        assert_eq!(view.to_patch().to_bytes(), bytes);
+    }
+
+    #[test]
+    fn apply() {
+        let source = fs::read("files/source.txt").unwrap();
+        let target = fs::read("files/target.txt").unwrap();
+        let bytes = Patch::new(&source, &target).to_bytes();
+        let view = PatchView::try_from(bytes.as_slice()).unwrap();
+        assert_eq!(view.apply(&source).unwrap(), target);
+        assert_eq!(Patch::from(view), Patch::try_from_bytes(&bytes).unwrap());
+    }
+
+    #[test]
+    fn new_err() {
+        for (bytes, error) in [
+            (&b"?\x01"[..], InstructionError::InvalidSign),
+            (b"-", InstructionError::MissingLength),
+            (b"+\x02A", InstructionError::MissingContent),
+            (b"@\x01\x00", InstructionError::MissingContent),
+            (
+                b"@\x01\xff\xff\xff\xff\xff\xff\xff\xff",
+                InstructionError::InvalidContent,
+            ),
+        ] {
+            assert_eq!(PatchView::new(bytes), Err(error.at(0, 0, bytes[0])));
+        }
+        assert_eq!(
+            PatchView::new(b"-\x01+"),
+            Err(InstructionError::MissingLength.at(1, 2, b'+'))
+        );
+        let bytes = Patch::new(b"AB", b"AC").to_compressed_bytes(Compression::None);
+        assert_eq!(
+            PatchView::new(&bytes),
+            Err(InstructionError::UnsupportedFeature)
+        );
+    }
+}